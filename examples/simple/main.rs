@@ -1,7 +1,5 @@
 //! run this example to see the generated help
 
-use clap::Parser;
-
 // call this macro with the names of all of your tasks
 // this will:
 //  - add `use my_task;` statements for each task
@@ -15,6 +13,10 @@ xtask_utils::tasks!(
 );
 
 fn main() -> ! {
-  // just parse the command line arguments and call the generated `run` method
-  Task::parse().run();
+  // parse the command line arguments and call the generated `run_all` method
+  // this supports running several tasks in one invocation
+  // e.g. `greet -- greet alice`
+  // to run exactly one task use `clap::Parser::parse` and the `run` method
+  // `Task::parse().run()`
+  Task::run_all();
 }
@@ -1,41 +1,456 @@
+use crate::exit::ProvideExitCode;
 use std::{
+  any::Any,
+  backtrace::Backtrace,
+  char::ParseCharError,
+  env::VarError,
   error::Error,
   fmt::{self, Display, Formatter},
+  io, iter,
+  num::{ParseFloatError, ParseIntError, TryFromIntError},
+  panic::Location,
+  slice,
+  str::{ParseBoolError, Utf8Error},
+  string::FromUtf8Error,
+  thread::JoinHandle,
+  time::SystemTimeError,
 };
 
+/// Create an [`AnyErr`] with a formatted message
+///
+/// ```
+/// # use xtask_utils::any_err;
+/// let err = any_err!("failed to build {}", "xtask-utils");
+/// assert_eq!(err.to_string(), "failed to build xtask-utils");
+/// ```
+#[macro_export]
+macro_rules! any_err {
+  ($($arg:tt)+) => {
+    $crate::any_err::AnyErr::new(::std::format!($($arg)+))
+  };
+}
+
+/// Return early with an [`AnyErr`] with a formatted message
+#[macro_export]
+macro_rules! bail {
+  ($($arg:tt)+) => {
+    return ::std::result::Result::Err($crate::any_err!($($arg)+))
+  };
+}
+
+/// Return early with an [`AnyErr`] if the condition is false. Without a
+/// message the error says which condition failed.
+#[macro_export]
+macro_rules! ensure {
+  ($cond:expr $(,)?) => {
+    $crate::ensure!(
+      $cond,
+      "condition failed: `{}`",
+      ::std::stringify!($cond)
+    )
+  };
+
+  ($cond:expr, $($arg:tt)+) => {
+    if !$cond {
+      $crate::bail!($($arg)+);
+    }
+  };
+}
+
+/// A boxed error that can be sent between threads
+pub type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+/// An error with a message, an optional source and some details.
+///
+/// The common error types from `std` and this crate convert into an `AnyErr`
+/// so `?` can be used on them in functions that return `Result<_, AnyErr>`.
+/// Other errors can be converted with [`ResultExt::context`] or
+/// [`AnyErr::from_boxed`]. There is no blanket `From<E: Error>` because
+/// `AnyErr` is an error itself and the impl would overlap with `From<T> for
+/// T`.
+///
+/// [`ResultExt::context`]: crate::result_ext::ResultExt::context
 #[derive(Debug)]
 pub struct AnyErr {
-  message: String,
-  source: Option<Box<dyn Error + 'static>>,
+  // boxed to keep `Result<T, AnyErr>` small
+  inner: Box<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+  /// `None` if this error is only a wrapper around its source, in which case
+  /// it displays as the source
+  message: Option<String>,
+  source: Option<BoxError>,
+  fields: Vec<(String, String)>,
+  notes: Vec<String>,
+  hints: Vec<String>,
+  exit_code: Option<i32>,
+  location: &'static Location<'static>,
+  /// Whether `location` is left out of reports, since it isn't meaningful
+  #[cfg(feature = "result_ext")]
+  hide_location: bool,
+  backtrace: Backtrace,
 }
 
 impl AnyErr {
+  /// Create an error with the given message. The location of the caller is
+  /// recorded and, if enabled with `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`,
+  /// a backtrace is captured.
+  #[track_caller]
   pub fn new(message: impl ToString) -> Self {
     Self {
-      message: message.to_string(),
-      source: None,
+      inner: Box::new(Inner {
+        message: Some(message.to_string()),
+        source: None,
+        fields: Vec::new(),
+        notes: Vec::new(),
+        hints: Vec::new(),
+        exit_code: None,
+        location: Location::caller(),
+        #[cfg(feature = "result_ext")]
+        hide_location: false,
+        backtrace: Backtrace::capture(),
+      }),
     }
   }
 
+  #[track_caller]
   pub fn with_source(
     message: impl ToString,
-    source: impl Error + 'static,
+    source: impl Error + Send + Sync + 'static,
   ) -> Self {
-    Self {
-      message: message.to_string(),
-      source: Some(Box::new(source)),
+    let mut err = Self::new(message);
+    err.inner.source = Some(Box::new(source));
+    err
+  }
+
+  /// Wrap a boxed error. The new error displays the same as the boxed one.
+  #[track_caller]
+  pub fn from_boxed(err: BoxError) -> Self {
+    let mut any_err = Self::new("");
+    any_err.inner.message = None;
+    any_err.inner.source = Some(err);
+    any_err
+  }
+
+  /// Turn the payload of a panic, as returned by
+  /// [`JoinHandle::join`](std::thread::JoinHandle::join) or
+  /// [`catch_unwind`](std::panic::catch_unwind), into an error
+  #[track_caller]
+  pub fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+    Self::new(format!("thread panicked: {}", panic_message(&*payload)))
+  }
+
+  /// Attach a value that describes the failure, e.g. the path of a file that
+  /// couldn't be read
+  pub fn field(mut self, key: impl ToString, value: impl ToString) -> Self {
+    self.inner.fields.push((key.to_string(), value.to_string()));
+    self
+  }
+
+  /// Attach a free-form note explaining the failure
+  pub fn note(mut self, note: impl ToString) -> Self {
+    self.inner.notes.push(note.to_string());
+    self
+  }
+
+  /// Attach a hint telling the user how to fix the failure
+  pub fn hint(mut self, hint: impl ToString) -> Self {
+    self.inner.hints.push(hint.to_string());
+    self
+  }
+
+  /// Exit with `code` instead of the code of the source, or 1, when this
+  /// error is reported
+  pub fn with_exit_code(mut self, code: i32) -> Self {
+    self.inner.exit_code = Some(code);
+    self
+  }
+
+  pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+    self
+      .inner
+      .fields
+      .iter()
+      .map(|(key, value)| (key.as_str(), value.as_str()))
+  }
+
+  /// The value of the first field called `key`
+  pub fn get_field(&self, key: &str) -> Option<&str> {
+    self
+      .fields()
+      .find_map(|(k, value)| (k == key).then_some(value))
+  }
+
+  pub fn notes(&self) -> impl Iterator<Item = &str> {
+    self.inner.notes.iter().map(String::as_str)
+  }
+
+  pub fn hints(&self) -> impl Iterator<Item = &str> {
+    self.inner.hints.iter().map(String::as_str)
+  }
+
+  /// Where in the source code this error was created
+  pub fn location(&self) -> &'static Location<'static> {
+    self.inner.location
+  }
+
+  /// Leave the location out of reports, e.g. for an error created by a panic
+  /// hook whose location says nothing about the panic
+  #[cfg(feature = "result_ext")]
+  pub(crate) fn hide_location(mut self) -> Self {
+    self.inner.hide_location = true;
+    self
+  }
+
+  /// The location to report, if any
+  #[cfg(feature = "result_ext")]
+  pub(crate) fn reported_location(&self) -> Option<&'static Location<'static>> {
+    (!self.inner.hide_location).then_some(self.inner.location)
+  }
+
+  /// The backtrace captured when this error was created. Check its
+  /// [`status`](Backtrace::status) to see if it was actually captured.
+  pub fn backtrace(&self) -> &Backtrace {
+    &self.inner.backtrace
+  }
+
+  /// This error followed by its sources. An error that only wraps another one
+  /// is replaced by the error it wraps, since they display the same.
+  pub fn chain(&self) -> impl Iterator<Item = &(dyn Error + 'static)> {
+    iter::successors(Some(unwrap_transparent(self)), |&err| {
+      err.source().map(unwrap_transparent)
+    })
+  }
+
+  /// The last error in the [`chain`](Self::chain)
+  pub fn root_cause(&self) -> &(dyn Error + 'static) {
+    self.chain().last().unwrap()
+  }
+
+  /// The first error of type `T` in the [`chain`](Self::chain)
+  ///
+  /// ```
+  /// # use std::{fs, io};
+  /// # use xtask_utils::any_err::AnyErr;
+  /// fn read_config() -> Result<String, AnyErr> {
+  ///   match fs::read_to_string("xtask.toml") {
+  ///     Ok(config) => Ok(config),
+  ///     Err(err) => Err(AnyErr::with_source("failed to read the config", err)),
+  ///   }
+  /// }
+  ///
+  /// if let Err(err) = read_config() {
+  ///   let not_found = err
+  ///     .downcast_ref::<io::Error>()
+  ///     .is_some_and(|err| err.kind() == io::ErrorKind::NotFound);
+  ///   assert!(not_found);
+  /// }
+  /// ```
+  pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+    self.chain().find_map(|err| err.downcast_ref())
+  }
+
+  /// The error this one wraps if it was created from another error without a
+  /// message of its own
+  pub(crate) fn wrapped(&self) -> Option<&(dyn Error + 'static)> {
+    match self.inner.message {
+      Some(_) => None,
+      None => Some(self.inner.source.as_deref()?),
     }
   }
 }
 
 impl Error for AnyErr {
   fn source(&self) -> Option<&(dyn Error + 'static)> {
-    self.source.as_deref()
+    let source = self.inner.source.as_deref()?;
+    match self.inner.message {
+      Some(_) => Some(source),
+      None => source.source(),
+    }
+  }
+}
+
+impl ProvideExitCode for AnyErr {
+  fn exit_code(&self) -> Option<i32> {
+    self.inner.exit_code
   }
 }
 
 impl Display for AnyErr {
   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-    Display::fmt(&self.message, f)
+    match (&self.inner.message, &self.inner.source) {
+      (Some(message), _) => Display::fmt(message, f),
+      (None, Some(source)) => Display::fmt(source, f),
+      (None, None) => Ok(()),
+    }
+  }
+}
+
+impl From<BoxError> for AnyErr {
+  #[track_caller]
+  fn from(err: BoxError) -> Self {
+    Self::from_boxed(err)
+  }
+}
+
+macro_rules! impl_from {
+  ($($err:ty),* $(,)?) => {
+    $(
+      impl From<$err> for AnyErr {
+        #[track_caller]
+        fn from(err: $err) -> Self {
+          Self::from_boxed(Box::new(err))
+        }
+      }
+    )*
+  };
+}
+
+impl_from!(
+  io::Error,
+  fmt::Error,
+  FromUtf8Error,
+  ParseBoolError,
+  ParseCharError,
+  ParseFloatError,
+  ParseIntError,
+  SystemTimeError,
+  TryFromIntError,
+  Utf8Error,
+  VarError,
+  Errors,
+  clap::Error,
+);
+
+#[cfg(feature = "command_ext")]
+impl_from!(crate::command_ext::Error);
+
+#[cfg(feature = "prompt")]
+impl_from!(crate::prompt::Cancelled);
+
+/// The message a panic was started with
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+  match payload.downcast_ref::<String>() {
+    Some(message) => message,
+    None => payload
+      .downcast_ref::<&str>()
+      .copied()
+      .unwrap_or("Box<dyn Any>"),
+  }
+}
+
+fn unwrap_transparent<'a>(
+  mut err: &'a (dyn Error + 'static),
+) -> &'a (dyn Error + 'static) {
+  while let Some(wrapped) =
+    err.downcast_ref::<AnyErr>().and_then(AnyErr::wrapped)
+  {
+    err = wrapped;
+  }
+  err
+}
+
+/// Several errors reported together, e.g. every invalid manifest found by a
+/// check that keeps going after the first one
+#[derive(Debug, Default)]
+pub struct Errors {
+  message: Option<String>,
+  errors: Vec<AnyErr>,
+}
+
+impl Errors {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Display `message` instead of the number of errors
+  pub fn with_message(message: impl ToString) -> Self {
+    Self {
+      message: Some(message.to_string()),
+      errors: Vec::new(),
+    }
+  }
+
+  #[track_caller]
+  pub fn push(&mut self, err: impl Into<AnyErr>) {
+    self.errors.push(err.into());
+  }
+
+  pub fn len(&self) -> usize {
+    self.errors.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.errors.is_empty()
+  }
+
+  pub fn iter(&self) -> slice::Iter<'_, AnyErr> {
+    self.errors.iter()
+  }
+
+  /// `Ok(value)` if no errors were pushed, otherwise `Err(self)`
+  pub fn into_result<T>(self, value: T) -> Result<T, Self> {
+    if self.is_empty() {
+      Ok(value)
+    } else {
+      Err(self)
+    }
+  }
+}
+
+impl Error for Errors {}
+
+impl Display for Errors {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match (&self.message, self.errors.len()) {
+      (Some(message), _) => Display::fmt(message, f),
+      (None, 1) => f.write_str("1 error occurred"),
+      (None, len) => write!(f, "{len} errors occurred"),
+    }
+  }
+}
+
+impl Extend<AnyErr> for Errors {
+  fn extend<I: IntoIterator<Item = AnyErr>>(&mut self, iter: I) {
+    self.errors.extend(iter);
+  }
+}
+
+impl FromIterator<AnyErr> for Errors {
+  fn from_iter<I: IntoIterator<Item = AnyErr>>(iter: I) -> Self {
+    Self {
+      message: None,
+      errors: iter.into_iter().collect(),
+    }
+  }
+}
+
+impl IntoIterator for Errors {
+  type Item = AnyErr;
+  type IntoIter = std::vec::IntoIter<AnyErr>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.errors.into_iter()
+  }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+  type Item = &'a AnyErr;
+  type IntoIter = slice::Iter<'a, AnyErr>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.errors.iter()
+  }
+}
+
+/// Wait for a thread to finish and return its result. If the thread panicked
+/// the panic is turned into an error.
+#[track_caller]
+pub fn join<T>(handle: JoinHandle<Result<T, AnyErr>>) -> Result<T, AnyErr> {
+  match handle.join() {
+    Ok(res) => res,
+    Err(payload) => Err(AnyErr::from_panic(payload)),
   }
 }
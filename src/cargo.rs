@@ -0,0 +1,355 @@
+//! Build cargo commands without assembling them from strings.
+//!
+//! ```no_run
+//! # use xtask_utils::{cargo::Cargo, prelude::*};
+//! Cargo::clippy()
+//!   .workspace()
+//!   .all_features()
+//!   .trailing_args(["-D", "warnings"])
+//!   .command()
+//!   .wait()
+//!   .exit_on_err();
+//! ```
+
+use self::message::{CompilerMessage, Diagnostic};
+#[cfg(feature = "script")]
+use crate::pipe::{JoinHandle, PipeIo, PipeSection};
+use crate::{
+  any_err::AnyErr,
+  command_ext::{self, ErrorKind},
+  result_ext::{report::ColorChoice, ResultExt},
+  timings::{Status, Timer},
+  workspace,
+};
+#[cfg(feature = "script")]
+use std::borrow::Cow;
+use std::{
+  ffi::OsString,
+  io::{BufRead, BufReader},
+  process::{Command, Stdio},
+};
+
+pub mod message;
+
+pub use self::message::{Artifact, Message};
+
+/// A cargo command. The cargo running the xtask is used if there is one.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Cargo {
+  subcommand: String,
+  packages: Vec<String>,
+  workspace: bool,
+  features: Vec<String>,
+  all_features: bool,
+  no_default_features: bool,
+  target: Option<String>,
+  profile: Option<String>,
+  json: bool,
+  ansi: bool,
+  args: Vec<OsString>,
+  trailing_args: Vec<OsString>,
+}
+
+impl Cargo {
+  pub fn new(subcommand: impl Into<String>) -> Self {
+    Self {
+      subcommand: subcommand.into(),
+      packages: Vec::new(),
+      workspace: false,
+      features: Vec::new(),
+      all_features: false,
+      no_default_features: false,
+      target: None,
+      profile: None,
+      json: false,
+      ansi: false,
+      args: Vec::new(),
+      trailing_args: Vec::new(),
+    }
+  }
+
+  pub fn build() -> Self {
+    Self::new("build")
+  }
+
+  pub fn check() -> Self {
+    Self::new("check")
+  }
+
+  pub fn clippy() -> Self {
+    Self::new("clippy")
+  }
+
+  pub fn test() -> Self {
+    Self::new("test")
+  }
+
+  pub fn run() -> Self {
+    Self::new("run")
+  }
+
+  /// `-p <name>`
+  pub fn package(mut self, name: impl Into<String>) -> Self {
+    self.packages.push(name.into());
+    self
+  }
+
+  /// `--workspace`
+  pub fn workspace(mut self) -> Self {
+    self.workspace = true;
+    self
+  }
+
+  /// Add a feature to `--features`
+  pub fn feature(mut self, feature: impl Into<String>) -> Self {
+    self.features.push(feature.into());
+    self
+  }
+
+  pub fn features<I, S>(mut self, features: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    self.features.extend(features.into_iter().map(Into::into));
+    self
+  }
+
+  /// `--all-features`
+  pub fn all_features(mut self) -> Self {
+    self.all_features = true;
+    self
+  }
+
+  /// `--no-default-features`
+  pub fn no_default_features(mut self) -> Self {
+    self.no_default_features = true;
+    self
+  }
+
+  /// `--target <triple>`
+  pub fn target(mut self, triple: impl Into<String>) -> Self {
+    self.target = Some(triple.into());
+    self
+  }
+
+  /// `--profile <name>`
+  pub fn profile(mut self, name: impl Into<String>) -> Self {
+    self.profile = Some(name.into());
+    self
+  }
+
+  /// The release profile
+  pub fn release(self) -> Self {
+    self.profile("release")
+  }
+
+  /// `--message-format=json`, see [`Message`]
+  pub fn json(mut self) -> Self {
+    self.json = true;
+    self
+  }
+
+  /// `--message-format=json-diagnostic-rendered-ansi`, JSON output with
+  /// colored rendered diagnostics
+  pub fn json_ansi(mut self) -> Self {
+    self.json = true;
+    self.ansi = true;
+    self
+  }
+
+  /// An argument for cargo itself, added after the options above
+  pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+    self.args.push(arg.into());
+    self
+  }
+
+  pub fn args<I, S>(mut self, args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+  {
+    self.args.extend(args.into_iter().map(Into::into));
+    self
+  }
+
+  /// Arguments passed after `--`, e.g. to the test harness or to rustc lints
+  /// for clippy
+  pub fn trailing_args<I, S>(mut self, args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+  {
+    self.trailing_args.extend(args.into_iter().map(Into::into));
+    self
+  }
+
+  pub fn command(&self) -> Command {
+    let mut command = workspace::cargo();
+    command.arg(&self.subcommand);
+    for package in &self.packages {
+      command.args(["-p", package.as_str()]);
+    }
+    if self.workspace {
+      command.arg("--workspace");
+    }
+    if !self.features.is_empty() {
+      command.args(["--features", &self.features.join(",")]);
+    }
+    if self.all_features {
+      command.arg("--all-features");
+    }
+    if self.no_default_features {
+      command.arg("--no-default-features");
+    }
+    if let Some(target) = &self.target {
+      command.args(["--target", target]);
+    }
+    if let Some(profile) = &self.profile {
+      command.args(["--profile", profile]);
+    }
+    if self.json && self.ansi {
+      command.arg("--message-format=json-diagnostic-rendered-ansi");
+    } else if self.json {
+      command.arg("--message-format=json");
+    }
+    command.args(&self.args);
+    if !self.trailing_args.is_empty() {
+      command.arg("--").args(&self.trailing_args);
+    }
+    command
+  }
+
+  /// Run the command with JSON output and return the messages cargo printed.
+  /// Fails if cargo fails, after printing the diagnostics to stderr as cargo
+  /// would print them.
+  pub fn messages(&self) -> Result<Vec<Message>, AnyErr> {
+    let name = format!("cargo {}", self.subcommand);
+    let error = |kind| command_ext::Error {
+      name: name.clone(),
+      kind,
+    };
+    let output = self
+      .json_for_stderr()
+      .command()
+      .stderr(Stdio::inherit())
+      .output()
+      .map_err(|err| error(ErrorKind::Spawn(err)))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let messages = message::parse(&stdout).collect::<Result<Vec<_>, _>>();
+
+    if !output.status.success() {
+      // without them there is nothing saying why the build failed
+      for message in message::parse(&stdout).flatten() {
+        if let Some(rendered) = rendered(&message) {
+          eprint!("{rendered}");
+        }
+      }
+      return Err(error(ErrorKind::Exit(output.status)).into());
+    }
+    messages.context("failed to parse the output of cargo")
+  }
+
+  /// Run the command with JSON output and return the artifacts it built. The
+  /// diagnostics are printed to stderr as cargo would print them and any
+  /// other output, like that of `cargo run`, is passed through to stdout.
+  pub fn artifacts(&self) -> Result<Vec<Artifact>, AnyErr> {
+    let command = self.json_for_stderr().command();
+    let name = format!("cargo {}", self.subcommand);
+    let timer = Timer::start(&name);
+    let res = read_artifacts(command, &name);
+    if let Some(timer) = timer {
+      timer.finish(match res {
+        Ok(_) => Status::Ok,
+        Err(_) => Status::Failed,
+      });
+    }
+    res
+  }
+
+  /// JSON output whose rendered diagnostics are colored if they are printed
+  /// to a terminal
+  fn json_for_stderr(&self) -> Self {
+    if ColorChoice::Auto.enabled() {
+      self.clone().json_ansi()
+    } else {
+      self.clone().json()
+    }
+  }
+}
+
+fn read_artifacts(
+  mut command: Command,
+  name: &str,
+) -> Result<Vec<Artifact>, AnyErr> {
+  let error = |kind| command_ext::Error {
+    name: name.to_owned(),
+    kind,
+  };
+  let mut child = command
+    .stdout(Stdio::piped())
+    .spawn()
+    .map_err(|err| error(ErrorKind::Spawn(err)))?;
+
+  let mut artifacts = Vec::new();
+  let stdout = BufReader::new(child.stdout.take().unwrap());
+  for line in stdout.lines() {
+    let line = line.context("failed to read the output of cargo")?;
+    // the program run by `cargo run` may print JSON too
+    let Ok(message) = serde_json::from_str(&line) else {
+      println!("{line}");
+      continue;
+    };
+    match message {
+      Message::CompilerArtifact(artifact) => artifacts.push(artifact),
+      message => {
+        if let Some(rendered) = rendered(&message) {
+          eprint!("{rendered}");
+        }
+      }
+    }
+  }
+
+  let status = child.wait().map_err(|err| error(ErrorKind::Wait(err)))?;
+  if !status.success() {
+    return Err(error(ErrorKind::Exit(status)).into());
+  }
+  Ok(artifacts)
+}
+
+/// A diagnostic as cargo would print it
+fn rendered(message: &Message) -> Option<&str> {
+  match message {
+    Message::CompilerMessage(CompilerMessage {
+      message: Diagnostic {
+        rendered: Some(rendered),
+        ..
+      },
+      ..
+    }) => Some(rendered),
+    _ => None,
+  }
+}
+
+#[cfg(feature = "script")]
+impl PipeSection for Cargo {
+  fn do_pipe(
+    &self,
+    input: PipeIo,
+  ) -> command_ext::Result<(PipeIo, JoinHandle)> {
+    self.command().do_pipe(input)
+  }
+
+  fn end_pipe(
+    &self,
+    input: PipeIo,
+    output: PipeIo,
+  ) -> command_ext::Result<JoinHandle> {
+    self.command().end_pipe(input, output)
+  }
+
+  fn describe(&self) -> Cow<'_, str> {
+    Cow::Owned(self.command().describe().into_owned())
+  }
+}
@@ -0,0 +1,104 @@
+//! The messages cargo prints with `--message-format=json`.
+
+use crate::workspace::Target;
+use serde::Deserialize;
+use std::{io::BufRead, path::PathBuf};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum Message {
+  CompilerArtifact(Artifact),
+  CompilerMessage(CompilerMessage),
+  BuildScriptExecuted(BuildScript),
+  BuildFinished(BuildFinished),
+  /// A message this crate doesn't know about
+  #[serde(other)]
+  Unknown,
+}
+
+/// A target that was built
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Artifact {
+  pub package_id: String,
+  pub target: Target,
+  /// The files produced for the target
+  pub filenames: Vec<PathBuf>,
+  /// The path of the binary, for targets that produce one
+  pub executable: Option<PathBuf>,
+  /// Whether the target was already up to date
+  pub fresh: bool,
+}
+
+/// A diagnostic from the compiler
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct CompilerMessage {
+  pub package_id: String,
+  pub target: Target,
+  pub message: Diagnostic,
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Diagnostic {
+  pub message: String,
+  /// e.g. `error`, `warning` or `note`
+  pub level: String,
+  pub code: Option<DiagnosticCode>,
+  /// The diagnostic as the compiler would print it
+  pub rendered: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct DiagnosticCode {
+  /// e.g. `E0308` or `clippy::needless_return`
+  pub code: String,
+}
+
+/// The output of a build script that ran
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct BuildScript {
+  pub package_id: String,
+  /// Libraries to link, from `cargo::rustc-link-lib`
+  pub linked_libs: Vec<String>,
+  /// Library search paths, from `cargo::rustc-link-search`
+  pub linked_paths: Vec<String>,
+  /// From `cargo::rustc-cfg`
+  pub cfgs: Vec<String>,
+  /// From `cargo::rustc-env`
+  pub env: Vec<(String, String)>,
+  /// The `OUT_DIR` of the build script
+  pub out_dir: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct BuildFinished {
+  pub success: bool,
+}
+
+/// Read the messages from cargo's output as it is written, e.g. from a file a
+/// `Pipe` wrote it to. Lines that aren't cargo messages, like the output of
+/// `cargo run`, are skipped.
+pub fn read<R: BufRead>(
+  reader: R,
+) -> impl Iterator<Item = Result<Message, serde_json::Error>> {
+  reader.lines().filter_map(|line| match line {
+    Ok(line) => serde_json::from_str(&line).ok().map(Ok),
+    Err(err) => Some(Err(serde_json::Error::io(err))),
+  })
+}
+
+/// Parse the messages in cargo's output. Lines that aren't cargo messages,
+/// like the output of `cargo run`, are skipped.
+pub fn parse(
+  output: &str,
+) -> impl Iterator<Item = Result<Message, serde_json::Error>> + '_ {
+  output
+    .lines()
+    .filter_map(|line| serde_json::from_str(line).ok().map(Ok))
+}
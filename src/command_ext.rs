@@ -1,10 +1,11 @@
+use crate::exit::{self, ProvideExitCode};
 #[cfg(unix)]
 use std::os::unix::process::CommandExt as UnixCommand;
 use std::{
   error::Error as StdError,
   fmt::{self, Display, Formatter},
   io,
-  process::{self, Command, ExitStatus},
+  process::{self, Command, ExitStatus, Stdio},
 };
 
 pub mod prelude {
@@ -12,11 +13,16 @@ pub mod prelude {
 }
 
 pub trait CommandExt {
+  /// Run the command and exit with its exit code. Only returns if the
+  /// command can't be run.
+  // rustc rejects `#[must_use]` on trait impl methods, so it only goes here
   #[must_use]
   fn exec(&mut self) -> Error;
 
   fn wait(&mut self) -> Result<()>;
 
+  fn read(&mut self) -> Result<String>;
+
   fn from_str(s: impl AsRef<str>) -> Self;
 }
 
@@ -24,7 +30,6 @@ impl CommandExt for Command {
   /// Run the command and exit with its exit code.
   ///
   /// On unix it uses `exec`
-  #[must_use]
   fn exec(&mut self) -> Error {
     #[cfg(unix)]
     return Error {
@@ -61,6 +66,23 @@ impl CommandExt for Command {
     }
   }
 
+  /// Run the command and return what it wrote to stdout. Stderr is inherited.
+  fn read(&mut self) -> Result<String> {
+    let name = self.get_program().to_string_lossy().into_owned();
+    let error = |kind| Error {
+      name: name.clone(),
+      kind,
+    };
+    let output = self
+      .stderr(Stdio::inherit())
+      .output()
+      .map_err(|err| error(ErrorKind::Spawn(err)))?;
+    if !output.status.success() {
+      return Err(error(ErrorKind::Exit(output.status)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+  }
+
   fn from_str(s: impl AsRef<str>) -> Self {
     let mut args = s.as_ref().split_whitespace();
     let arg0 = args.next().expect("arg0 not supplied");
@@ -91,7 +113,7 @@ impl ExitStatusExt for ExitStatus {
 
 fn print_status_and_exit(status: &ExitStatus) -> ! {
   eprintln!("\n--------------------------------------\n{}", status);
-  process::exit(status.code().unwrap_or(1));
+  process::exit(exit::status_code(status));
 }
 
 #[derive(Debug)]
@@ -117,6 +139,22 @@ impl StdError for Error {
   }
 }
 
+impl ProvideExitCode for Error {
+  /// The code the command exited with, or the one a shell would use if it
+  /// couldn't be run
+  fn exit_code(&self) -> Option<i32> {
+    match &self.kind {
+      ErrorKind::Exit(status) => Some(exit::status_code(status)),
+      ErrorKind::Spawn(err) => match err.kind() {
+        io::ErrorKind::NotFound => Some(127),
+        io::ErrorKind::PermissionDenied => Some(126),
+        _ => None,
+      },
+      ErrorKind::File(_) | ErrorKind::Wait(_) => None,
+    }
+  }
+}
+
 impl Display for Error {
   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
     let Self { name, kind } = self;
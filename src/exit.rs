@@ -0,0 +1,118 @@
+//! Exit codes of failed xtasks.
+//!
+//! When an error is printed and the process exits, the code is taken from the
+//! first error in its chain that provides one, or is 1 if none does. A
+//! command that failed provides the code it exited with, or 127 if it wasn't
+//! found, so CI sees the same code it would have seen running it directly.
+
+use std::{
+  error::Error,
+  process::{ExitCode, ExitStatus},
+  sync::RwLock,
+};
+
+/// The exit code used when no error in the chain provides one
+pub const DEFAULT_CODE: i32 = 1;
+
+/// Implemented by errors that know what code the process should exit with
+pub trait ProvideExitCode {
+  fn exit_code(&self) -> Option<i32>;
+}
+
+/// Implemented by the values a successful result can exit with, see
+/// [`ResultExt::exit_with_code`](crate::result_ext::ResultExt::exit_with_code)
+pub trait ExitValue {
+  fn exit_code(&self) -> i32;
+}
+
+impl ExitValue for () {
+  fn exit_code(&self) -> i32 {
+    0
+  }
+}
+
+impl ExitValue for ExitCode {
+  fn exit_code(&self) -> i32 {
+    // there is no stable way to read the code back, but it can be compared
+    (0..=u8::MAX)
+      .find(|&code| ExitCode::from(code) == *self)
+      .map_or(DEFAULT_CODE, i32::from)
+  }
+}
+
+impl ExitValue for ExitStatus {
+  fn exit_code(&self) -> i32 {
+    status_code(self)
+  }
+}
+
+type Provider = fn(&(dyn Error + 'static)) -> Option<i32>;
+
+static PROVIDERS: RwLock<Vec<Provider>> = RwLock::new(Vec::new());
+
+/// Use the exit codes provided by errors of type `T`. The error types of
+/// this crate don't need to be registered.
+pub fn register<T: Error + ProvideExitCode + 'static>() {
+  fn provide<T: Error + ProvideExitCode + 'static>(
+    err: &(dyn Error + 'static),
+  ) -> Option<i32> {
+    err.downcast_ref::<T>()?.exit_code()
+  }
+
+  let mut providers = PROVIDERS.write().unwrap_or_else(|err| err.into_inner());
+  providers.push(provide::<T>);
+}
+
+/// The code to exit with after reporting `err`
+pub fn code_of(err: &(dyn Error + 'static)) -> i32 {
+  let providers = PROVIDERS.read().unwrap_or_else(|err| err.into_inner());
+  let mut next = Some(err);
+  while let Some(err) = next {
+    let code = builtin_code(err)
+      .or_else(|| providers.iter().find_map(|provide| provide(err)));
+    if let Some(code) = code {
+      return code;
+    }
+    next = wrapped(err).or_else(|| err.source());
+  }
+  DEFAULT_CODE
+}
+
+/// The code of a process that exited with `status`. A process killed by a
+/// signal gets 128 plus the signal number, like in a shell.
+pub fn status_code(status: &ExitStatus) -> i32 {
+  #[cfg(unix)]
+  if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(status) {
+    return 128 + signal;
+  }
+  status.code().unwrap_or(DEFAULT_CODE)
+}
+
+#[allow(unused_variables)]
+fn builtin_code(err: &(dyn Error + 'static)) -> Option<i32> {
+  #[cfg(feature = "any_err")]
+  if let Some(err) = err.downcast_ref::<crate::any_err::AnyErr>() {
+    return err.exit_code();
+  }
+  #[cfg(feature = "command_ext")]
+  if let Some(err) = err.downcast_ref::<crate::command_ext::Error>() {
+    return err.exit_code();
+  }
+  #[cfg(feature = "prompt")]
+  if let Some(err) = err.downcast_ref::<crate::prompt::Cancelled>() {
+    return err.exit_code();
+  }
+  None
+}
+
+/// The error wrapped by a transparent `AnyErr`, which isn't its source
+#[allow(unused_variables)]
+fn wrapped<'a>(
+  err: &'a (dyn Error + 'static),
+) -> Option<&'a (dyn Error + 'static)> {
+  #[cfg(feature = "any_err")]
+  if let Some(err) = err.downcast_ref::<crate::any_err::AnyErr>() {
+    return err.wrapped();
+  }
+  None
+}
@@ -0,0 +1,205 @@
+//! Wrappers around [`std::fs`] whose errors say what failed and on which path.
+//!
+//! Besides the functions mirroring `std::fs` there are [`copy_dir`] to copy
+//! a directory tree, [`write_atomic`] so readers never see a partially
+//! written file and [`write_if_changed`] which leaves the file, and its
+//! modification time, alone if it already has the contents.
+
+use crate::{
+  any_err::AnyErr,
+  result_ext::{ErrorExt, ResultExt},
+};
+use std::{fs, io, path::Path, process};
+
+#[track_caller]
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<u8>, AnyErr> {
+  let path = path.as_ref();
+  fs::read(path).context(format!("failed to read {}", path.display()))
+}
+
+#[track_caller]
+pub fn read_to_string(path: impl AsRef<Path>) -> Result<String, AnyErr> {
+  let path = path.as_ref();
+  fs::read_to_string(path).context(format!("failed to read {}", path.display()))
+}
+
+#[track_caller]
+pub fn write(
+  path: impl AsRef<Path>,
+  contents: impl AsRef<[u8]>,
+) -> Result<(), AnyErr> {
+  let path = path.as_ref();
+  fs::write(path, contents)
+    .context(format!("failed to write {}", path.display()))
+}
+
+/// Copy the contents of a file, returning the number of bytes copied
+#[track_caller]
+pub fn copy(
+  from: impl AsRef<Path>,
+  to: impl AsRef<Path>,
+) -> Result<u64, AnyErr> {
+  let (from, to) = (from.as_ref(), to.as_ref());
+  fs::copy(from, to).context(format!(
+    "failed to copy {} to {}",
+    from.display(),
+    to.display()
+  ))
+}
+
+#[track_caller]
+pub fn rename(
+  from: impl AsRef<Path>,
+  to: impl AsRef<Path>,
+) -> Result<(), AnyErr> {
+  let (from, to) = (from.as_ref(), to.as_ref());
+  fs::rename(from, to).context(format!(
+    "failed to rename {} to {}",
+    from.display(),
+    to.display()
+  ))
+}
+
+#[track_caller]
+pub fn create_dir_all(path: impl AsRef<Path>) -> Result<(), AnyErr> {
+  let path = path.as_ref();
+  fs::create_dir_all(path)
+    .context(format!("failed to create directory {}", path.display()))
+}
+
+#[track_caller]
+pub fn remove_file(path: impl AsRef<Path>) -> Result<(), AnyErr> {
+  let path = path.as_ref();
+  fs::remove_file(path).context(format!("failed to remove {}", path.display()))
+}
+
+#[track_caller]
+pub fn remove_dir_all(path: impl AsRef<Path>) -> Result<(), AnyErr> {
+  let path = path.as_ref();
+  fs::remove_dir_all(path)
+    .context(format!("failed to remove directory {}", path.display()))
+}
+
+/// Copy every file in the directory `from` to the same place under `to`,
+/// creating directories as needed. Returns the number of files copied.
+///
+/// Symlinks aren't followed. On Unix they are copied as links pointing to
+/// the same target, elsewhere copying one is an error, so filter them out with
+/// [`copy_dir_with`].
+#[track_caller]
+pub fn copy_dir(
+  from: impl AsRef<Path>,
+  to: impl AsRef<Path>,
+) -> Result<usize, AnyErr> {
+  copy_dir_with(from, to, |_| true)
+}
+
+/// Like [`copy_dir`], but only copies the files and directories for which
+/// `filter` returns true. The filter is given paths relative to `from`.
+#[track_caller]
+pub fn copy_dir_with(
+  from: impl AsRef<Path>,
+  to: impl AsRef<Path>,
+  mut filter: impl FnMut(&Path) -> bool,
+) -> Result<usize, AnyErr> {
+  let (from, to) = (from.as_ref(), to.as_ref());
+  copy_dir_inner(from, to, Path::new(""), &mut filter)
+}
+
+#[track_caller]
+fn copy_dir_inner(
+  from: &Path,
+  to: &Path,
+  relative: &Path,
+  filter: &mut dyn FnMut(&Path) -> bool,
+) -> Result<usize, AnyErr> {
+  let dir = from.join(relative);
+  create_dir_all(to.join(relative))?;
+  let entries = fs::read_dir(&dir)
+    .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+    .context(format!("failed to read directory {}", dir.display()))?;
+
+  let mut copied = 0;
+  for entry in entries {
+    let path = relative.join(entry.file_name());
+    if !filter(&path) {
+      continue;
+    }
+    let file_type = entry.file_type().context(format!(
+      "failed to read the type of {}",
+      entry.path().display()
+    ))?;
+    if file_type.is_symlink() {
+      copied += copy_symlink(&from.join(&path), &to.join(&path))?;
+    } else if file_type.is_dir() {
+      copied += copy_dir_inner(from, to, &path, filter)?;
+    } else {
+      copy(from.join(&path), to.join(&path))?;
+      copied += 1;
+    }
+  }
+  Ok(copied)
+}
+
+/// Create a link at `to` with the target of the link `from`, returning the
+/// number of links copied
+#[cfg(unix)]
+#[track_caller]
+fn copy_symlink(from: &Path, to: &Path) -> Result<usize, AnyErr> {
+  let target = fs::read_link(from)
+    .context(format!("failed to read link {}", from.display()))?;
+  // like `copy`, replace what is already there
+  if fs::symlink_metadata(to).is_ok() {
+    remove_file(to)?;
+  }
+  std::os::unix::fs::symlink(&target, to)
+    .context(format!("failed to create link {}", to.display()))?;
+  Ok(1)
+}
+
+#[cfg(not(unix))]
+#[track_caller]
+fn copy_symlink(from: &Path, _to: &Path) -> Result<usize, AnyErr> {
+  Err(AnyErr::new(format!(
+    "failed to copy link {}, links can only be copied on Unix",
+    from.display()
+  )))
+}
+
+/// Write to a temporary file next to `path` and rename it to `path`, so
+/// `path` either has its old or its new contents, never a partial write
+#[track_caller]
+pub fn write_atomic(
+  path: impl AsRef<Path>,
+  contents: impl AsRef<[u8]>,
+) -> Result<(), AnyErr> {
+  let path = path.as_ref();
+  let mut temp = path.as_os_str().to_owned();
+  temp.push(format!(".tmp-{}", process::id()));
+
+  let res = fs::write(&temp, contents).and_then(|()| fs::rename(&temp, path));
+  if res.is_err() {
+    fs::remove_file(&temp).ok();
+  }
+  res.context(format!("failed to write {}", path.display()))
+}
+
+/// Write `contents` to `path` unless it already has them. Returns whether the
+/// file was written.
+#[track_caller]
+pub fn write_if_changed(
+  path: impl AsRef<Path>,
+  contents: impl AsRef<[u8]>,
+) -> Result<bool, AnyErr> {
+  let (path, contents) = (path.as_ref(), contents.as_ref());
+  match fs::read(path) {
+    Ok(current) if current == contents => return Ok(false),
+    Ok(_) => {}
+    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+    Err(err) => {
+      return Err(err.context(format!("failed to read {}", path.display())))
+    }
+  }
+  write(path, contents)?;
+  Ok(true)
+}
@@ -0,0 +1,40 @@
+//! Just enough JSON writing for the machine-readable outputs of this crate
+
+use std::fmt::Write;
+
+/// Quote and escape `s` as a JSON string
+pub(crate) fn string(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+/// Join already serialized values into a JSON array
+pub(crate) fn array(items: impl IntoIterator<Item = String>) -> String {
+  let items: Vec<_> = items.into_iter().collect();
+  format!("[{}]", items.join(","))
+}
+
+/// Join keys and already serialized values into a JSON object
+#[cfg(feature = "result_ext")]
+pub(crate) fn object<'a>(
+  entries: impl IntoIterator<Item = (&'a str, String)>,
+) -> String {
+  let entries: Vec<_> = entries
+    .into_iter()
+    .map(|(key, value)| format!("{}:{value}", string(key)))
+    .collect();
+  format!("{{{}}}", entries.join(","))
+}
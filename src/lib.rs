@@ -5,16 +5,40 @@ pub mod prelude {
   pub use super::command_ext::prelude::*;
   #[cfg(feature = "result_ext")]
   pub use super::result_ext::prelude::*;
+  #[cfg(feature = "workspace")]
+  pub use super::workspace::prelude::*;
 }
 
 #[cfg(feature = "any_err")]
 pub mod any_err;
+#[cfg(feature = "cargo")]
+pub mod cargo;
 #[cfg(feature = "command_ext")]
 pub mod command_ext;
+pub mod exit;
+#[cfg(feature = "fs")]
+pub mod fs;
+mod json;
 #[cfg(feature = "script")]
 pub mod pipe;
+#[cfg(feature = "prompt")]
+pub mod prompt;
 #[cfg(feature = "result_ext")]
 pub mod result_ext;
+#[cfg(feature = "scaffold")]
+pub mod scaffold;
+#[cfg(feature = "step")]
+pub mod step;
+pub mod task;
+pub mod timings;
+#[cfg(feature = "tools")]
+pub mod tools;
+#[cfg(feature = "walk")]
+pub mod walk;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "workspace")]
+pub mod workspace;
 
 #[macro_export]
 macro_rules! tasks {
@@ -33,6 +57,18 @@ macro_rules! tasks {
           $(Task::$name(cli) => self::$name::main(cli)),*
         }
       }
+
+      /// Parse one or more tasks from the command line and run them in order
+      #[allow(dead_code)]
+      fn run_all() -> ! {
+        $crate::task::run_all::<Self>()
+      }
+    }
+
+    impl $crate::task::Tasks for Task {
+      fn run(self) -> ! {
+        Task::run(self)
+      }
     }
   };
 }
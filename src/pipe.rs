@@ -1,10 +1,13 @@
 use crate::{
   command_ext::{Error, ErrorKind, Result},
+  exit::ExitValue,
   prelude::*,
+  timings::{Status, Timer},
 };
 use std::{
   borrow::Cow,
-  convert, fs,
+  fmt::{self, Display, Formatter},
+  fs,
   fs::File,
   path::{Path, PathBuf},
   process::{Child, ChildStdout, Command, ExitStatus, Stdio},
@@ -44,7 +47,7 @@ macro_rules! cmd {
 /// The output of the final one is piped to the given output
 #[must_use]
 pub struct Pipe<'p> {
-  commands: Box<dyn DoubleEndedIterator<Item = &'p dyn PipeSection> + 'p>,
+  commands: Vec<&'p dyn PipeSection>,
   output: PipeIo,
 }
 
@@ -52,61 +55,35 @@ impl<'p> Pipe<'p> {
   pub fn new<I>(commands: I, output: impl Into<PipeIo>) -> Self
   where
     I: IntoIterator<Item = &'p dyn PipeSection>,
-    I::IntoIter: DoubleEndedIterator + 'p,
   {
     Self {
-      commands: Box::new(commands.into_iter()),
+      commands: commands.into_iter().collect(),
       output: output.into(),
     }
   }
 
   pub fn spawn(self) -> Result<JoinHandle> {
-    self.impl_(convert::identity, convert::identity)
-  }
-
-  pub fn exec(self) -> ! {
-    self
-      .impl_(|err| err.exit(), |handle| handle.join().exit())
-      .exit()
-  }
-
-  pub fn wait(self) -> Result<()> {
-    self
-      .spawn()
-      .map(|handle| handle.join().exit_on_err().exit_on_err())
-  }
-
-  fn impl_<T>(
-    mut self,
-    mut on_err: impl FnMut(Error) -> Error,
-    join: impl FnOnce(JoinHandle) -> T,
-  ) -> Result<T> {
-    let first = match self.commands.next() {
+    let mut commands = self.commands.into_iter();
+    let first = match commands.next() {
       Some(first) => first,
       None => panic!("pipe was called with no commands"),
     };
 
-    let last = match self.commands.next_back() {
+    let last = match commands.next_back() {
       Some(last) => last,
-      None => {
-        let handle = first
-          .end_pipe(PipeIo::Inherit, self.output)
-          .map_err(&mut on_err)?;
-        return Ok(join(handle));
-      }
+      None => return first.end_pipe(PipeIo::Inherit, self.output),
     };
 
     let mut handles = Vec::new();
-    let (mut previous, handle) =
-      first.do_pipe(PipeIo::Inherit).map_err(&mut on_err)?;
+    let (mut previous, handle) = first.do_pipe(PipeIo::Inherit)?;
     handles.push(handle);
 
-    for command in self.commands {
+    for command in commands {
       let (io, handle) = match command.do_pipe(previous) {
         Ok(handle) => handle,
         Err(err) => {
           handles.into_iter().for_each(JoinHandle::cancel);
-          return Err(on_err(err));
+          return Err(err);
         }
       };
       previous = io;
@@ -121,7 +98,50 @@ impl<'p> Pipe<'p> {
       }
     }
 
-    Ok(join(JoinHandle::Multiple(handles)))
+    Ok(JoinHandle::Multiple(handles))
+  }
+
+  pub fn exec(self) -> ! {
+    let timer = Timer::start(&self);
+    let joined = self.spawn().and_then(JoinHandle::join);
+    if let Some(timer) = timer {
+      timer.finish(step_status(&joined));
+    }
+    joined.exit_with_code()
+  }
+
+  pub fn wait(self) -> Result<()> {
+    let timer = Timer::start(&self);
+    let joined = self.spawn().map(JoinHandle::join);
+    if let Some(timer) = timer {
+      timer.finish(match &joined {
+        Ok(joined) => step_status(joined),
+        Err(_) => Status::Failed,
+      });
+    }
+    joined.map(|joined| joined.exit_on_err().exit_on_err())
+  }
+}
+
+impl Display for Pipe<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    for (idx, command) in self.commands.iter().enumerate() {
+      if idx > 0 {
+        f.write_str(" | ")?;
+      }
+      f.write_str(&command.describe())?;
+    }
+    if let PipeIo::File(path) = &self.output {
+      write!(f, " > {}", path.display())?;
+    }
+    Ok(())
+  }
+}
+
+fn step_status(joined: &Result<Joined>) -> Status {
+  match joined {
+    Ok(joined) if joined.success() => Status::Ok,
+    _ => Status::Failed,
   }
 }
 
@@ -129,6 +149,11 @@ pub trait PipeSection {
   fn do_pipe(&self, input: PipeIo) -> Result<(PipeIo, JoinHandle)>;
 
   fn end_pipe(&self, input: PipeIo, output: PipeIo) -> Result<JoinHandle>;
+
+  /// A short description of this section used when reporting on the pipe
+  fn describe(&self) -> Cow<'_, str> {
+    Cow::Borrowed("<command>")
+  }
 }
 
 impl PipeSection for &str {
@@ -158,6 +183,10 @@ impl PipeSection for &str {
       })?;
     Ok(JoinHandle::Cmd(child))
   }
+
+  fn describe(&self) -> Cow<'_, str> {
+    Cow::Borrowed(self)
+  }
 }
 
 impl PipeSection for String {
@@ -168,6 +197,10 @@ impl PipeSection for String {
   fn end_pipe(&self, input: PipeIo, output: PipeIo) -> Result<JoinHandle> {
     self.as_str().end_pipe(input, output)
   }
+
+  fn describe(&self) -> Cow<'_, str> {
+    Cow::Borrowed(self)
+  }
 }
 
 impl PipeSection for Cow<'_, str> {
@@ -184,6 +217,72 @@ impl PipeSection for Cow<'_, str> {
       Cow::Owned(s) => s.end_pipe(input, output),
     }
   }
+
+  fn describe(&self) -> Cow<'_, str> {
+    Cow::Borrowed(self)
+  }
+}
+
+/// The command is copied, so spawning it doesn't change the original
+impl PipeSection for Command {
+  fn do_pipe(&self, input: PipeIo) -> Result<(PipeIo, JoinHandle)> {
+    let mut command = copy(self);
+    let mut child = command
+      .stdin(TryInto::<Stdio>::try_into(input)?)
+      .stdout(Stdio::piped())
+      .spawn()
+      .map_err(|err| Error {
+        name: command.get_program().to_string_lossy().into_owned(),
+        kind: ErrorKind::Spawn(err),
+      })?;
+    let io = child.stdout.take().unwrap().into();
+    Ok((io, JoinHandle::Cmd(child)))
+  }
+
+  fn end_pipe(&self, input: PipeIo, output: PipeIo) -> Result<JoinHandle> {
+    let mut command = copy(self);
+    let child = command
+      .stdin(TryInto::<Stdio>::try_into(input)?)
+      .stdout(TryInto::<Stdio>::try_into(output)?)
+      .spawn()
+      .map_err(|err| Error {
+        name: command.get_program().to_string_lossy().into_owned(),
+        kind: ErrorKind::Spawn(err),
+      })?;
+    Ok(JoinHandle::Cmd(child))
+  }
+
+  fn describe(&self) -> Cow<'_, str> {
+    let program = Path::new(self.get_program());
+    let mut description = program
+      .file_stem()
+      .unwrap_or(program.as_os_str())
+      .to_string_lossy()
+      .into_owned();
+    for arg in self.get_args() {
+      description.push(' ');
+      description.push_str(&arg.to_string_lossy());
+    }
+    Cow::Owned(description)
+  }
+}
+
+/// A command with the same program, arguments, environment and working
+/// directory as `command`. Its stdio isn't copied since there are no getters
+/// for it.
+fn copy(command: &Command) -> Command {
+  let mut copy = Command::new(command.get_program());
+  copy.args(command.get_args());
+  for (key, value) in command.get_envs() {
+    match value {
+      Some(value) => copy.env(key, value),
+      None => copy.env_remove(key),
+    };
+  }
+  if let Some(dir) = command.get_current_dir() {
+    copy.current_dir(dir);
+  }
+  copy
 }
 
 pub enum PipeIo {
@@ -279,10 +378,12 @@ impl JoinHandle {
     }
   }
 
+  /// Kill the commands and wait for them to exit
   pub fn cancel(self) {
     match self {
       JoinHandle::Cmd(mut cmd) => {
         cmd.kill().ok();
+        cmd.wait().ok();
       }
       JoinHandle::Multiple(handles) => {
         for handle in handles {
@@ -299,6 +400,20 @@ pub enum Joined {
   Multiple(Vec<Joined>),
 }
 
+impl ExitValue for Joined {
+  /// The code of the first command that failed, or 0 if they all succeeded
+  fn exit_code(&self) -> i32 {
+    match self {
+      Joined::Cmd(status) => status.exit_code(),
+      Joined::Multiple(joined) => joined
+        .iter()
+        .map(Joined::exit_code)
+        .find(|&code| code != 0)
+        .unwrap_or(0),
+    }
+  }
+}
+
 impl Joined {
   pub fn success(&self) -> bool {
     match self {
@@ -0,0 +1,315 @@
+//! Ask the user before doing something destructive.
+//!
+//! Prompts are written to stderr and read from stdin. When stdin isn't a
+//! terminal, e.g. in CI, they use their default and fail if they have none.
+//! The `--yes` global option of [`tasks!`](crate::tasks) answers yes to every
+//! [`Confirm`] and uses the defaults of the other prompts.
+//!
+//! ```no_run
+//! # use xtask_utils::{prelude::*, prompt::{Confirm, Select}};
+//! let registry = Select::new("Publish to", ["crates.io", "internal"])
+//!   .default(0)
+//!   .ask()
+//!   .exit_on_err();
+//! // exits unless the user answers yes
+//! Confirm::new("Publish version 1.2.0?").require().exit_on_err();
+//! ```
+
+use crate::{
+  any_err::AnyErr,
+  exit::ProvideExitCode,
+  result_ext::{ErrorExt, ResultExt},
+};
+use std::{
+  env,
+  error::Error,
+  fmt::{self, Display, Formatter},
+  io::{self, BufRead, IsTerminal, Write},
+};
+
+/// Set by the `--yes` global option
+pub(crate) const YES_ENV: &str = "XTASK_UTILS_YES";
+
+/// Whether prompts should be answered without asking
+pub fn assume_yes() -> bool {
+  env::var_os(YES_ENV).is_some()
+}
+
+/// The user declined a [`Confirm`] or closed stdin instead of answering.
+/// Exits with code 130, like a command interrupted with Ctrl-C.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl Error for Cancelled {}
+
+impl Display for Cancelled {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str("cancelled")
+  }
+}
+
+impl ProvideExitCode for Cancelled {
+  fn exit_code(&self) -> Option<i32> {
+    Some(130)
+  }
+}
+
+/// A yes or no question
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Confirm {
+  question: String,
+  default: Option<bool>,
+}
+
+impl Confirm {
+  pub fn new(question: impl Into<String>) -> Self {
+    Self {
+      question: question.into(),
+      default: None,
+    }
+  }
+
+  /// The answer if the user just presses enter, or if stdin isn't a
+  /// terminal. Without a default enter means no and a missing terminal is an
+  /// error.
+  pub fn default(mut self, default: bool) -> Self {
+    self.default = Some(default);
+    self
+  }
+
+  #[track_caller]
+  pub fn ask(&self) -> Result<bool, AnyErr> {
+    match (mode(), self.default) {
+      (Mode::Ask, _) => self.ask_with(io::stdin().lock(), io::stderr()),
+      (Mode::Yes, _) => Ok(true),
+      (Mode::NoTerminal, Some(default)) => Ok(default),
+      (Mode::NoTerminal, None) => Err(no_terminal(&self.question)),
+    }
+  }
+
+  /// Fail with [`Cancelled`] unless the answer is yes
+  #[track_caller]
+  pub fn require(&self) -> Result<(), AnyErr> {
+    if self.ask()? {
+      Ok(())
+    } else {
+      Err(Cancelled.into())
+    }
+  }
+
+  /// Ask on `input` and `output` instead of the terminal, e.g. in tests
+  pub fn ask_with(
+    &self,
+    mut input: impl BufRead,
+    mut output: impl Write,
+  ) -> Result<bool, AnyErr> {
+    let prompt = match self.default {
+      Some(true) => format!("{} [Y/n] ", self.question),
+      Some(false) | None => format!("{} [y/N] ", self.question),
+    };
+    loop {
+      write_prompt(&mut output, &prompt)?;
+      match read_answer(&mut input)?.to_lowercase().as_str() {
+        "" => return Ok(self.default.unwrap_or(false)),
+        "y" | "yes" => return Ok(true),
+        "n" | "no" => return Ok(false),
+        _ => write_prompt(&mut output, "please answer y or n\n")?,
+      }
+    }
+  }
+}
+
+/// A choice from a list of options. The answer is the index of the option.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Select {
+  prompt: String,
+  options: Vec<String>,
+  default: Option<usize>,
+}
+
+impl Select {
+  pub fn new<I, S>(prompt: impl Into<String>, options: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: ToString,
+  {
+    Self {
+      prompt: prompt.into(),
+      options: options
+        .into_iter()
+        .map(|option| option.to_string())
+        .collect(),
+      default: None,
+    }
+  }
+
+  /// The index of the option used if the user just presses enter, with
+  /// `--yes` or if stdin isn't a terminal
+  pub fn default(mut self, index: usize) -> Self {
+    self.default = Some(index);
+    self
+  }
+
+  #[track_caller]
+  pub fn ask(&self) -> Result<usize, AnyErr> {
+    self.check()?;
+    match (mode(), self.default) {
+      (Mode::Ask, _) => self.ask_with(io::stdin().lock(), io::stderr()),
+      (_, Some(default)) => Ok(default),
+      (Mode::Yes, None) => Err(no_default(&self.prompt)),
+      (Mode::NoTerminal, None) => Err(no_terminal(&self.prompt)),
+    }
+  }
+
+  /// Ask on `input` and `output` instead of the terminal, e.g. in tests
+  #[track_caller]
+  pub fn ask_with(
+    &self,
+    mut input: impl BufRead,
+    mut output: impl Write,
+  ) -> Result<usize, AnyErr> {
+    self.check()?;
+    write_prompt(&mut output, &format!("{}\n", self.prompt))?;
+    for (idx, option) in self.options.iter().enumerate() {
+      let default = if self.default == Some(idx) {
+        " (default)"
+      } else {
+        ""
+      };
+      let number = idx + 1;
+      write_prompt(&mut output, &format!("  {number}) {option}{default}\n"))?;
+    }
+    loop {
+      let range = format!("[1-{}]", self.options.len());
+      write_prompt(&mut output, &format!("{range} "))?;
+      let answer = read_answer(&mut input)?;
+      match (answer.parse::<usize>(), self.default) {
+        (_, Some(default)) if answer.is_empty() => return Ok(default),
+        (Ok(number), _) if (1..=self.options.len()).contains(&number) => {
+          return Ok(number - 1)
+        }
+        _ => write_prompt(
+          &mut output,
+          &format!("please enter a number from {range}\n"),
+        )?,
+      }
+    }
+  }
+
+  /// There must be an option to choose and the default must be one of them
+  #[track_caller]
+  fn check(&self) -> Result<(), AnyErr> {
+    if self.options.is_empty() {
+      return Err(AnyErr::new(format!("\"{}\" has no options", self.prompt)));
+    }
+    match self.default {
+      Some(default) if default >= self.options.len() => Err(
+        AnyErr::new(format!(
+          "the default of \"{}\" is not one of its options",
+          self.prompt
+        ))
+        .field("default", default)
+        .field("options", self.options.len()),
+      ),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// A line of text
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Input {
+  prompt: String,
+  default: Option<String>,
+}
+
+impl Input {
+  pub fn new(prompt: impl Into<String>) -> Self {
+    Self {
+      prompt: prompt.into(),
+      default: None,
+    }
+  }
+
+  /// The answer if the user just presses enter, with `--yes` or if stdin
+  /// isn't a terminal
+  pub fn default(mut self, default: impl Into<String>) -> Self {
+    self.default = Some(default.into());
+    self
+  }
+
+  #[track_caller]
+  pub fn ask(&self) -> Result<String, AnyErr> {
+    match (mode(), &self.default) {
+      (Mode::Ask, _) => self.ask_with(io::stdin().lock(), io::stderr()),
+      (_, Some(default)) => Ok(default.clone()),
+      (Mode::Yes, None) => Err(no_default(&self.prompt)),
+      (Mode::NoTerminal, None) => Err(no_terminal(&self.prompt)),
+    }
+  }
+
+  /// Ask on `input` and `output` instead of the terminal, e.g. in tests
+  pub fn ask_with(
+    &self,
+    mut input: impl BufRead,
+    mut output: impl Write,
+  ) -> Result<String, AnyErr> {
+    let prompt = match &self.default {
+      Some(default) => format!("{} [{default}] ", self.prompt),
+      None => format!("{} ", self.prompt),
+    };
+    write_prompt(&mut output, &prompt)?;
+    let answer = read_answer(&mut input)?;
+    match &self.default {
+      Some(default) if answer.is_empty() => Ok(default.clone()),
+      _ => Ok(answer),
+    }
+  }
+}
+
+enum Mode {
+  Ask,
+  Yes,
+  NoTerminal,
+}
+
+fn mode() -> Mode {
+  if assume_yes() {
+    Mode::Yes
+  } else if io::stdin().is_terminal() {
+    Mode::Ask
+  } else {
+    Mode::NoTerminal
+  }
+}
+
+#[track_caller]
+fn no_terminal(prompt: &str) -> AnyErr {
+  AnyErr::new(format!("can't ask \"{prompt}\", stdin is not a terminal"))
+    .hint("pass --yes to answer yes and use the defaults")
+}
+
+#[track_caller]
+fn no_default(prompt: &str) -> AnyErr {
+  AnyErr::new(format!("\"{prompt}\" has no default to use with --yes"))
+}
+
+fn write_prompt(output: &mut impl Write, prompt: &str) -> Result<(), AnyErr> {
+  output
+    .write_all(prompt.as_bytes())
+    .and_then(|()| output.flush())
+    .context("failed to write the prompt")
+}
+
+/// The trimmed line the user entered. Closing stdin cancels.
+fn read_answer(input: &mut impl BufRead) -> Result<String, AnyErr> {
+  let mut answer = String::new();
+  match input.read_line(&mut answer) {
+    Ok(0) => Err(Cancelled.into()),
+    Ok(_) => Ok(answer.trim().to_owned()),
+    Err(err) => Err(err.context("failed to read the answer")),
+  }
+}
@@ -1,70 +1,119 @@
-use crate::any_err::AnyErr;
+use crate::{
+  any_err::{AnyErr, Errors},
+  exit::{self, ExitValue},
+};
 use std::{error::Error, process};
 
+pub mod panic;
+pub mod report;
+
 pub mod prelude {
-  pub use super::{ErrorExt, ResultExt};
+  pub use super::{ErrorExt, ResultExt, ResultIterExt};
 }
 
 pub trait ResultExt<T, E> {
-  fn exit(&self) -> !;
+  /// Exit with 0, or report the error and exit with its code
+  fn exit(&self) -> !
+  where
+    E: AsDynError;
+
+  /// Exit with the code of the `Ok` value, e.g. the status of a command that
+  /// ran, or report the error and exit with its code
+  fn exit_with_code(&self) -> !
+  where
+    T: ExitValue,
+    E: AsDynError;
 
-  fn exit_on_err(self) -> T;
+  fn exit_on_err(self) -> T
+  where
+    E: AsDynError;
 
+  #[track_caller]
   fn context(self, message: impl ToString) -> Result<T, AnyErr>
   where
-    E: 'static;
+    E: Send + Sync + 'static;
 
+  #[track_caller]
   fn with_context<F, C>(self, f: F) -> Result<T, AnyErr>
   where
-    E: 'static,
+    E: Send + Sync + 'static,
     F: FnOnce() -> C,
     C: ToString;
 }
 
 impl<T, E: Error> ResultExt<T, E> for Result<T, E> {
-  fn exit(&self) -> ! {
+  fn exit(&self) -> !
+  where
+    E: AsDynError,
+  {
     match self {
       Ok(_) => process::exit(0),
-      Err(err) => {
-        print_err(err);
-        process::exit(1);
-      }
+      Err(err) => err.exit(),
     }
   }
 
-  fn exit_on_err(self) -> T {
+  fn exit_with_code(&self) -> !
+  where
+    T: ExitValue,
+    E: AsDynError,
+  {
+    match self {
+      Ok(value) => process::exit(value.exit_code()),
+      Err(err) => err.exit(),
+    }
+  }
+
+  fn exit_on_err(self) -> T
+  where
+    E: AsDynError,
+  {
     match self {
       Ok(obj) => obj,
       Err(err) => err.exit(),
     }
   }
 
+  // these match instead of using `map_err` so that `#[track_caller]` sees
+  // the caller of this method rather than a closure
+  #[track_caller]
   fn context(self, message: impl ToString) -> Result<T, AnyErr>
   where
-    E: 'static,
+    E: Send + Sync + 'static,
   {
-    self.map_err(|source| source.context(message))
+    match self {
+      Ok(obj) => Ok(obj),
+      Err(source) => Err(source.context(message)),
+    }
   }
 
+  #[track_caller]
   fn with_context<F, C>(self, f: F) -> Result<T, AnyErr>
   where
-    E: 'static,
+    E: Send + Sync + 'static,
     F: FnOnce() -> C,
     C: ToString,
   {
-    self.map_err(|source| source.context(f()))
+    match self {
+      Ok(obj) => Ok(obj),
+      Err(source) => Err(source.context(f())),
+    }
   }
 }
 
 pub trait ErrorExt: Error {
-  fn exit(&self) -> ! {
-    print_err(self);
-    process::exit(1);
+  fn exit(&self) -> !
+  where
+    Self: AsDynError,
+  {
+    let err = self.as_dyn_error();
+    report::print(err);
+    process::exit(exit::code_of(err));
   }
 
+  #[track_caller]
   fn context(self, message: impl ToString) -> AnyErr
   where
-    Self: Sized + 'static,
+    Self: Sized + Send + Sync + 'static,
   {
     AnyErr::with_source(message.to_string(), self)
   }
@@ -72,19 +121,59 @@ pub trait ErrorExt: Error {
 
 impl<T: Error + ?Sized> ErrorExt for T {}
 
-fn print_err<E: Error + ?Sized>(err: &E) {
-  eprintln!("{err}");
-  if let Some(mut err) = err.source() {
-    eprintln!();
-    eprintln!("Caused by:");
-    eprintln!("  {err}");
-
-    loop {
-      err = match err.source() {
-        Some(err) => err,
-        None => break,
-      };
-      eprintln!("  {err}");
-    }
+/// Errors that can be viewed as a `dyn Error`, which reporting and finding
+/// the exit code need. Unlike `Sized` errors this includes `dyn Error`
+/// itself, e.g. the contents of a `Box<dyn Error>`.
+pub trait AsDynError {
+  fn as_dyn_error(&self) -> &(dyn Error + 'static);
+}
+
+impl<T: Error + 'static> AsDynError for T {
+  fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+    self
+  }
+}
+
+impl AsDynError for dyn Error + 'static {
+  fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+    self
+  }
+}
+
+impl AsDynError for dyn Error + Send + 'static {
+  fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+    self
+  }
+}
+
+impl AsDynError for dyn Error + Send + Sync + 'static {
+  fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+    self
+  }
+}
+
+pub trait ResultIterExt<T, E>: Iterator<Item = Result<T, E>> {
+  /// Collect the `Ok` values, or every error if there were any. Unlike
+  /// collecting into a `Result` this doesn't stop at the first error.
+  #[track_caller]
+  fn collect_errors<C>(self) -> Result<C, Errors>
+  where
+    Self: Sized,
+    C: FromIterator<T>,
+    E: Into<AnyErr>,
+  {
+    let mut errors = Errors::new();
+    let values: Vec<T> = self
+      .filter_map(|res| match res {
+        Ok(value) => Some(value),
+        Err(err) => {
+          errors.push(err);
+          None
+        }
+      })
+      .collect();
+    errors.into_result(values.into_iter().collect())
   }
 }
+
+impl<T, E, I: Iterator<Item = Result<T, E>>> ResultIterExt<T, E> for I {}
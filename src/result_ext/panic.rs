@@ -0,0 +1,70 @@
+//! Report panics the same way as errors.
+
+use super::report;
+use crate::any_err::{self, AnyErr};
+use std::{panic, process, thread};
+
+/// The exit code Rust uses for a panic
+pub const DEFAULT_PANIC_CODE: i32 = 101;
+
+/// A panic hook that prints panics with the installed
+/// [`Reporter`](report::Reporter) instead of the default panic message and
+/// exits the process.
+///
+/// Since the process exits, a panic on another thread can no longer be
+/// caught or turned into an error by [`any_err::join`].
+#[must_use]
+pub struct PanicHook {
+  bug_message: Option<String>,
+  exit_code: i32,
+}
+
+impl Default for PanicHook {
+  fn default() -> Self {
+    Self {
+      bug_message: None,
+      exit_code: DEFAULT_PANIC_CODE,
+    }
+  }
+}
+
+impl PanicHook {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Print `message` as a note, e.g. "this is a bug in the xtask, please
+  /// report it"
+  pub fn bug_message(mut self, message: impl ToString) -> Self {
+    self.bug_message = Some(message.to_string());
+    self
+  }
+
+  /// Exit with `code` instead of 101
+  pub fn exit_code(mut self, code: i32) -> Self {
+    self.exit_code = code;
+    self
+  }
+
+  /// Replace the current panic hook
+  pub fn install(self) {
+    panic::set_hook(Box::new(move |info| {
+      let thread = thread::current();
+      // created here, so its own location would point into the hook
+      let mut err = AnyErr::new(format!(
+        "thread '{}' panicked: {}",
+        thread.name().unwrap_or("<unnamed>"),
+        any_err::panic_message(info.payload()),
+      ))
+      .hide_location();
+      if let Some(location) = info.location() {
+        err = err.field("location", location);
+      }
+      if let Some(message) = &self.bug_message {
+        err = err.note(message);
+      }
+      report::print(&err);
+      process::exit(self.exit_code);
+    }));
+  }
+}
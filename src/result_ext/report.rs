@@ -0,0 +1,316 @@
+//! How errors are printed by [`ResultExt::exit`](super::ResultExt::exit) and
+//! [`ErrorExt::exit`](super::ErrorExt::exit).
+//!
+//! The default is [`Full`]. A different [`Reporter`] can be installed with
+//! [`set_reporter`], e.g. [`Json`] when running in CI.
+
+use crate::{
+  any_err::{AnyErr, Errors},
+  json,
+};
+use std::{
+  backtrace::{Backtrace, BacktraceStatus},
+  env,
+  error::Error,
+  fmt::Display,
+  io::{self, IsTerminal, Write},
+  iter,
+  sync::RwLock,
+};
+
+static REPORTER: RwLock<Option<Box<dyn Reporter>>> = RwLock::new(None);
+
+/// Renders an error for the user
+pub trait Reporter: Send + Sync {
+  fn report(
+    &self,
+    err: &(dyn Error + 'static),
+    out: &mut dyn Write,
+  ) -> io::Result<()>;
+}
+
+/// Use `reporter` for every error printed from now on
+pub fn set_reporter(reporter: impl Reporter + 'static) {
+  let mut current = REPORTER.write().unwrap_or_else(|err| err.into_inner());
+  *current = Some(Box::new(reporter));
+}
+
+/// Print `err` to stderr with the installed reporter
+pub(crate) fn print(err: &(dyn Error + 'static)) {
+  let reporter = REPORTER.read().unwrap_or_else(|err| err.into_inner());
+  let mut stderr = io::stderr().lock();
+  // there is nowhere left to report a failure to write to stderr
+  match reporter.as_deref() {
+    Some(reporter) => reporter.report(err, &mut stderr).ok(),
+    None => Full::default().report(err, &mut stderr).ok(),
+  };
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+  /// Use colors if stderr is a terminal, unless `NO_COLOR` is set.
+  /// `CLICOLOR_FORCE` enables them regardless.
+  #[default]
+  Auto,
+  Always,
+  Never,
+}
+
+impl ColorChoice {
+  pub(crate) fn enabled(self) -> bool {
+    let var = |name| env::var_os(name).filter(|val| !val.is_empty());
+    match self {
+      ColorChoice::Auto => match var("CLICOLOR_FORCE") {
+        Some(force) if force != "0" => true,
+        _ => var("NO_COLOR").is_none() && io::stderr().is_terminal(),
+      },
+      ColorChoice::Always => true,
+      ColorChoice::Never => false,
+    }
+  }
+}
+
+/// The message, its causes, the details attached to any [`AnyErr`] in the
+/// chain and, if a backtrace was captured, locations and the backtrace. The
+/// errors in an aggregate are printed as a numbered list.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Full {
+  pub color: ColorChoice,
+}
+
+impl Reporter for Full {
+  fn report(
+    &self,
+    err: &(dyn Error + 'static),
+    out: &mut dyn Write,
+  ) -> io::Result<()> {
+    let printer = Printer {
+      out,
+      color: self.color.enabled(),
+      backtraces: captured_backtrace(err).is_some(),
+    };
+    printer.print(err)
+  }
+}
+
+/// The innermost backtrace captured by an [`AnyErr`] in the chain, which is
+/// the one closest to where things went wrong. Whether one is captured
+/// depends on `RUST_BACKTRACE` and `RUST_LIB_BACKTRACE`.
+fn captured_backtrace<'a>(
+  err: &'a (dyn Error + 'static),
+) -> Option<&'a Backtrace> {
+  iter::successors(Some(err), |&err| err.source())
+    .filter_map(|err| err.downcast_ref::<AnyErr>())
+    .map(AnyErr::backtrace)
+    .filter(|backtrace| backtrace.status() == BacktraceStatus::Captured)
+    .last()
+}
+
+struct Printer<'a> {
+  out: &'a mut dyn Write,
+  color: bool,
+  backtraces: bool,
+}
+
+impl Printer<'_> {
+  const BOLD: &'static str = "1";
+  const RED: &'static str = "1;31";
+  const CYAN: &'static str = "1;36";
+  const GREEN: &'static str = "1;32";
+  const DIM: &'static str = "2";
+
+  fn paint(&self, style: &str, text: impl Display) -> String {
+    if self.color {
+      format!("\x1b[{style}m{text}\x1b[0m")
+    } else {
+      text.to_string()
+    }
+  }
+
+  fn print(mut self, err: &(dyn Error + 'static)) -> io::Result<()> {
+    self.print_error(err, "", "")?;
+
+    if let Some(backtrace) = captured_backtrace(err) {
+      writeln!(self.out)?;
+      writeln!(self.out, "{}", self.paint(Self::BOLD, "Backtrace:"))?;
+      writeln!(self.out, "{backtrace}")?;
+    }
+    Ok(())
+  }
+
+  /// Print an error with its causes and details. The first line starts with
+  /// `label`, every line is indented by `indent`.
+  fn print_error(
+    &mut self,
+    err: &(dyn Error + 'static),
+    indent: &str,
+    label: &str,
+  ) -> io::Result<()> {
+    writeln!(self.out, "{indent}{label}{}", self.paint(Self::RED, err))?;
+    let indent = format!("{indent}{:width$}", "", width = label.len());
+    self.location(&indent, err)?;
+
+    let chain: Vec<_> =
+      iter::successors(Some(err), |&err| err.source()).collect();
+    if chain.len() > 1 {
+      writeln!(self.out)?;
+      writeln!(self.out, "{indent}{}", self.paint(Self::BOLD, "Caused by:"))?;
+      for err in &chain[1..] {
+        writeln!(self.out, "{indent}  {err}")?;
+        self.location(&format!("{indent}  "), *err)?;
+      }
+    }
+
+    let details: Vec<_> = chain
+      .iter()
+      .filter_map(|err| err.downcast_ref::<AnyErr>())
+      .collect();
+    if details.iter().any(|err| err.fields().next().is_some()) {
+      writeln!(self.out)?;
+      for (key, value) in details.iter().flat_map(|err| err.fields()) {
+        let key = self.paint(Self::BOLD, format!("{key}:"));
+        writeln!(self.out, "{indent}  {key} {value}")?;
+      }
+    }
+    if details
+      .iter()
+      .any(|err| err.notes().chain(err.hints()).next().is_some())
+    {
+      writeln!(self.out)?;
+      for note in details.iter().flat_map(|err| err.notes()) {
+        let label = self.paint(Self::CYAN, "note:");
+        writeln!(self.out, "{indent}{label} {note}")?;
+      }
+      for hint in details.iter().flat_map(|err| err.hints()) {
+        let label = self.paint(Self::GREEN, "hint:");
+        writeln!(self.out, "{indent}{label} {hint}")?;
+      }
+    }
+
+    if let Some(errors) = chain.iter().find_map(|&err| aggregate(err)) {
+      for (idx, err) in errors.iter().enumerate() {
+        writeln!(self.out)?;
+        self.print_error(err, &indent, &format!("{}. ", idx + 1))?;
+      }
+    }
+    Ok(())
+  }
+
+  fn location(
+    &mut self,
+    indent: &str,
+    err: &(dyn Error + 'static),
+  ) -> io::Result<()> {
+    let location = err
+      .downcast_ref::<AnyErr>()
+      .and_then(AnyErr::reported_location);
+    match (self.backtraces, location) {
+      (true, Some(location)) => {
+        let location = self.paint(Self::DIM, format!("at {location}"));
+        writeln!(self.out, "{indent}    {location}")
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+/// The message and its causes on a single line, separated by colons. The
+/// errors in an aggregate follow in brackets.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Compact;
+
+impl Reporter for Compact {
+  fn report(
+    &self,
+    err: &(dyn Error + 'static),
+    out: &mut dyn Write,
+  ) -> io::Result<()> {
+    writeln!(out, "{}", compact(err))
+  }
+}
+
+fn compact(err: &(dyn Error + 'static)) -> String {
+  let chain: Vec<_> =
+    iter::successors(Some(err), |&err| err.source()).collect();
+  let mut line = chain
+    .iter()
+    .map(ToString::to_string)
+    .collect::<Vec<_>>()
+    .join(": ");
+  if let Some(errors) = chain.iter().find_map(|&err| aggregate(err)) {
+    let errors: Vec<_> = errors.iter().map(|err| compact(err)).collect();
+    line.push_str(&format!(" [{}]", errors.join("; ")));
+  }
+  line
+}
+
+/// A single line of JSON with the message, causes, location and details, for
+/// tools that parse the output
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Reporter for Json {
+  fn report(
+    &self,
+    err: &(dyn Error + 'static),
+    out: &mut dyn Write,
+  ) -> io::Result<()> {
+    writeln!(out, "{}", to_json(err))
+  }
+}
+
+fn to_json(err: &(dyn Error + 'static)) -> String {
+  let chain: Vec<_> =
+    iter::successors(Some(err), |&err| err.source()).collect();
+  let details: Vec<_> = chain
+    .iter()
+    .filter_map(|err| err.downcast_ref::<AnyErr>())
+    .collect();
+  let location = match err
+    .downcast_ref::<AnyErr>()
+    .and_then(AnyErr::reported_location)
+  {
+    Some(location) => json::string(&location.to_string()),
+    None => String::from("null"),
+  };
+  let errors = chain.iter().find_map(|&err| aggregate(err));
+
+  json::object([
+    ("message", json::string(&err.to_string())),
+    (
+      "causes",
+      json::array(chain[1..].iter().map(|err| json::string(&err.to_string()))),
+    ),
+    ("location", location),
+    (
+      "fields",
+      json::object(
+        details
+          .iter()
+          .flat_map(|err| err.fields())
+          .map(|(key, value)| (key, json::string(value))),
+      ),
+    ),
+    (
+      "notes",
+      json::array(details.iter().flat_map(|err| err.notes()).map(json::string)),
+    ),
+    (
+      "hints",
+      json::array(details.iter().flat_map(|err| err.hints()).map(json::string)),
+    ),
+    (
+      "errors",
+      json::array(errors.into_iter().flatten().map(|err| to_json(err))),
+    ),
+  ])
+}
+
+fn aggregate<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a Errors> {
+  err.downcast_ref::<Errors>().or_else(|| {
+    err
+      .downcast_ref::<AnyErr>()?
+      .wrapped()?
+      .downcast_ref::<Errors>()
+  })
+}
@@ -0,0 +1,381 @@
+//! Add new tasks to an xtask crate.
+//!
+//! [`NewTask`] writes a module for the task from a template and adds its name
+//! to the crate's [`tasks!`](crate::tasks) invocation.
+
+use crate::{any_err::AnyErr, bail, fs, result_ext::ResultExt};
+use std::{fmt::Write, path::PathBuf};
+
+/// What the generated `main` function of a task looks like
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Returns {
+  /// `main` exits the process itself
+  #[default]
+  Never,
+  /// `main` calls a `run` function returning `Result<(), AnyErr>` and exits
+  /// with its result
+  Result,
+}
+
+/// A command line argument of the generated task
+#[derive(Clone, Debug)]
+pub struct Arg {
+  name: String,
+  kind: ArgKind,
+  help: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ArgKind {
+  Positional,
+  Flag,
+  Option,
+}
+
+impl Arg {
+  /// A required positional argument
+  pub fn positional(name: impl Into<String>) -> Self {
+    Self::new(name, ArgKind::Positional)
+  }
+
+  /// A `--name` switch
+  pub fn flag(name: impl Into<String>) -> Self {
+    Self::new(name, ArgKind::Flag)
+  }
+
+  /// An optional `--name <VALUE>` option
+  pub fn option(name: impl Into<String>) -> Self {
+    Self::new(name, ArgKind::Option)
+  }
+
+  fn new(name: impl Into<String>, kind: ArgKind) -> Self {
+    Self {
+      name: name.into(),
+      kind,
+      help: None,
+    }
+  }
+
+  /// The help text of the argument, written as its doc comment
+  pub fn help(mut self, help: impl Into<String>) -> Self {
+    self.help = Some(help.into());
+    self
+  }
+}
+
+/// Scaffold a new task
+#[must_use]
+pub struct NewTask {
+  src_root: PathBuf,
+  name: String,
+  about: Option<String>,
+  returns: Returns,
+  args: Vec<Arg>,
+}
+
+impl NewTask {
+  /// Scaffold the task called `name` in the xtask crate whose `main.rs` is in
+  /// `src_root`
+  pub fn new(src_root: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+    Self {
+      src_root: src_root.into(),
+      name: name.into(),
+      about: None,
+      returns: Returns::default(),
+      args: Vec::new(),
+    }
+  }
+
+  /// The description of the task, written as the doc comment of its `Cli`
+  pub fn about(mut self, about: impl Into<String>) -> Self {
+    self.about = Some(about.into());
+    self
+  }
+
+  pub fn returns(mut self, returns: Returns) -> Self {
+    self.returns = returns;
+    self
+  }
+
+  pub fn arg(mut self, arg: Arg) -> Self {
+    self.args.push(arg);
+    self
+  }
+
+  /// Write the task's module and add it to `tasks!`. Returns the path of the
+  /// new module.
+  pub fn create(self) -> Result<PathBuf, AnyErr> {
+    check_name("task", &self.name)?;
+    for arg in &self.args {
+      check_name("argument", &arg.name)?;
+    }
+
+    let mut path = self.src_root.join(&self.name);
+    path.set_extension("rs");
+    if path.exists() {
+      bail!(
+        "a task with the name `{}` already exists at {}",
+        self.name,
+        path.display()
+      );
+    }
+
+    let main_path = self.src_root.join("main.rs");
+    let main = fs::read_to_string(&main_path)?;
+    let main = add_to_tasks(&main, &self.name)
+      .with_context(|| format!("failed to update {}", main_path.display()))?;
+
+    let newline = newline(&main);
+    fs::write(&path, self.render().replace('\n', newline))?;
+    fs::write(&main_path, main)?;
+
+    Ok(path)
+  }
+
+  fn render(&self) -> String {
+    let mut out = String::from("use clap::Parser;\n");
+    if self.returns == Returns::Result {
+      out.push_str("use xtask_utils::{any_err::AnyErr, prelude::*};\n");
+    }
+
+    out.push_str("\n#[derive(Parser)]\n");
+    if let Some(about) = &self.about {
+      doc_comment(&mut out, "", about);
+    }
+    if self.args.is_empty() {
+      out.push_str("pub struct Cli {}\n");
+    } else {
+      out.push_str("pub struct Cli {\n");
+      for (idx, arg) in self.args.iter().enumerate() {
+        if idx > 0 {
+          out.push('\n');
+        }
+        if let Some(help) = &arg.help {
+          doc_comment(&mut out, "    ", help);
+        }
+        let name = &arg.name;
+        match arg.kind {
+          ArgKind::Positional => writeln!(out, "    {name}: String,"),
+          ArgKind::Flag => {
+            writeln!(out, "    #[arg(long)]\n    {name}: bool,")
+          }
+          ArgKind::Option => {
+            writeln!(out, "    #[arg(long)]\n    {name}: Option<String>,")
+          }
+        }
+        .unwrap();
+      }
+      out.push_str("}\n");
+    }
+
+    let fields: Vec<_> =
+      self.args.iter().map(|arg| arg.name.as_str()).collect();
+    let cli = match fields.as_slice() {
+      [] => String::from("Cli {}"),
+      fields => format!("Cli {{ {} }}", fields.join(", ")),
+    };
+    match self.returns {
+      Returns::Never => write!(
+        out,
+        "\npub fn main({cli}: Cli) -> ! {{\n    std::process::exit(0);\n}}\n"
+      ),
+      Returns::Result => write!(
+        out,
+        "\npub fn main(cli: Cli) -> ! {{\n    run(cli).exit();\n}}\n\n\
+         fn run({cli}: Cli) -> Result<(), AnyErr> {{\n    Ok(())\n}}\n"
+      ),
+    }
+    .unwrap();
+    out
+  }
+}
+
+fn doc_comment(out: &mut String, indent: &str, text: &str) {
+  for line in text.lines() {
+    if line.is_empty() {
+      writeln!(out, "{indent}///").unwrap();
+    } else {
+      writeln!(out, "{indent}/// {line}").unwrap();
+    }
+  }
+}
+
+fn check_name(what: &str, name: &str) -> Result<(), AnyErr> {
+  let mut chars = name.chars();
+  let snake_case = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+    && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+  if !snake_case {
+    bail!("the {what} name `{name}` is not in snake case");
+  }
+  if KEYWORDS.contains(&name) {
+    bail!("the {what} name `{name}` is a rust keyword");
+  }
+  Ok(())
+}
+
+const KEYWORDS: &[&str] = &[
+  "abstract", "as", "async", "await", "become", "box", "break", "const",
+  "continue", "crate", "do", "dyn", "else", "enum", "extern", "false", "final",
+  "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match",
+  "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+  "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+  "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn newline(s: &str) -> &'static str {
+  if s.contains("\r\n") {
+    "\r\n"
+  } else {
+    "\n"
+  }
+}
+
+/// Add `name` to the `tasks!` invocation in `main`. If the existing names are
+/// sorted the new one is inserted in order, otherwise it goes at the end.
+fn add_to_tasks(main: &str, name: &str) -> Result<String, AnyErr> {
+  let (start, end) = find_tasks(main)?;
+  let body = &main[start..end];
+  let entries = entries(body);
+
+  if entries.iter().any(|entry| entry.name == name) {
+    bail!("`{name}` is already listed in the tasks! invocation");
+  }
+
+  let sorted = entries.windows(2).all(|pair| pair[0].name <= pair[1].name);
+  let next = if sorted {
+    entries.iter().find(|entry| entry.name > name)
+  } else {
+    None
+  };
+
+  let newline = newline(main);
+  let indentation = match entries.first() {
+    Some(entry) if body.contains('\n') => {
+      let line_start = body[..entry.start].rfind('\n').map_or(0, |idx| idx + 1);
+      &body[line_start..entry.start]
+    }
+    _ => "  ",
+  };
+  let line = format!("{indentation}{name}{newline}");
+
+  let (idx, insert) = match (next, entries.last()) {
+    // on its own line before the next task and the comments above it
+    (Some(next), _) if next.own_line => (next.line_start, line),
+    (Some(next), _) => (next.start, format!("{name} ")),
+    // on its own line after the last task
+    (None, Some(last)) if last.own_line => match body[last.end..].find('\n') {
+      Some(idx) => (last.end + idx + 1, line),
+      None => (last.end, format!("{newline}{line}")),
+    },
+    (None, Some(last)) => (last.end, format!(" {name}")),
+    // the list is empty
+    (None, None) => match body.find('\n') {
+      Some(idx) => (idx + 1, line),
+      None => (0, name.to_owned()),
+    },
+  };
+
+  let mut main = main.to_owned();
+  main.insert_str(start + idx, &insert);
+  Ok(main)
+}
+
+/// The byte range between the delimiters of the `tasks!` invocation
+fn find_tasks(main: &str) -> Result<(usize, usize), AnyErr> {
+  let not_found = || AnyErr::new("could not find a usage of the tasks! macro");
+  let mut code = code_chars(main);
+  code
+    .find(|&(idx, _)| main[idx..].starts_with("tasks!"))
+    .ok_or_else(not_found)?;
+  // skip the rest of `tasks!`
+  code.nth("tasks!".len() - 2);
+
+  let (open, close) = match code.find(|(_, c)| !c.is_whitespace()) {
+    Some((open, '(')) => (open, ')'),
+    Some((open, '[')) => (open, ']'),
+    Some((open, '{')) => (open, '}'),
+    _ => return Err(not_found()),
+  };
+  let (end, _) = code.find(|&(_, c)| c == close).ok_or_else(|| {
+    AnyErr::new("the tasks! macro is missing its closing delimiter")
+  })?;
+  Ok((open + 1, end))
+}
+
+/// The characters of `src` and their byte offsets, skipping comments and
+/// string literals
+fn code_chars(src: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+  let mut chars = src.char_indices().peekable();
+  std::iter::from_fn(move || loop {
+    let (idx, c) = chars.next()?;
+    match (c, chars.peek().map(|&(_, next)| next)) {
+      ('/', Some('/')) => {
+        chars.find(|&(_, c)| c == '\n');
+      }
+      ('/', Some('*')) => {
+        chars.next();
+        let mut prev = ' ';
+        chars
+          .find(|&(_, c)| std::mem::replace(&mut prev, c) == '*' && c == '/');
+      }
+      ('"', _) => {
+        let mut escaped = false;
+        chars.find(|&(_, c)| {
+          let end = c == '"' && !escaped;
+          escaped = c == '\\' && !escaped;
+          end
+        });
+      }
+      _ => return Some((idx, c)),
+    }
+  })
+}
+
+/// A task name listed in the body of the `tasks!` invocation
+struct Entry<'a> {
+  name: &'a str,
+  /// byte offset of the name in the body
+  start: usize,
+  end: usize,
+  /// whether the name is the only one on its line
+  own_line: bool,
+  /// start of the comments directly above the name, or of its own line if
+  /// there are none
+  line_start: usize,
+}
+
+fn entries(body: &str) -> Vec<Entry<'_>> {
+  let mut entries = Vec::new();
+  let mut comments_start = None;
+  let mut offset = 0;
+  for line in body.split_inclusive('\n') {
+    let code = match line.find("//") {
+      Some(idx) => &line[..idx],
+      None => line,
+    };
+
+    if code.trim().is_empty() {
+      if line.trim_start().starts_with("//") {
+        comments_start.get_or_insert(offset);
+      } else {
+        comments_start = None;
+      }
+    } else {
+      let line_start = comments_start.take().unwrap_or(offset);
+      let own_line = code.split_whitespace().count() == 1;
+      for name in code.split_whitespace() {
+        let start = offset + (name.as_ptr() as usize - line.as_ptr() as usize);
+        entries.push(Entry {
+          name,
+          start,
+          end: start + name.len(),
+          own_line: own_line && body.contains('\n'),
+          line_start,
+        });
+      }
+    }
+
+    offset += line.len();
+  }
+  entries
+}
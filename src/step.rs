@@ -0,0 +1,202 @@
+//! Skip steps whose inputs haven't changed since they last ran.
+//!
+//! A [`Step`] hashes the contents of its input files together with its
+//! command line and stores the hash in `xtask-cache` in the target directory
+//! after running successfully. The next time it only runs if the hash
+//! changed or one of its outputs is missing. The `--force` global option of
+//! [`tasks!`](crate::tasks) runs every step regardless.
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! # use xtask_utils::{prelude::*, step::Step, walk::Walk};
+//! Step::new("protos")
+//!   .inputs(Walk::new("proto").include("*.proto"))
+//!   .output("src/generated.rs")
+//!   .run(|| Command::from_str("protoc --rust_out=src proto/api.proto").wait())
+//!   .exit_on_err();
+//! ```
+//!
+//! With the `script` feature `Step::pipe` runs a `cmd!` and adds its
+//! command line to the fingerprint.
+
+#[cfg(feature = "script")]
+use crate::pipe::Pipe;
+use crate::{
+  any_err::AnyErr,
+  fs,
+  timings::{self, Status},
+  walk::Walk,
+  workspace,
+};
+use std::{
+  env,
+  fmt::Display,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+/// Set by the `--force` global option
+pub(crate) const FORCE_ENV: &str = "XTASK_UTILS_FORCE";
+
+/// Whether steps should run even if they are up to date
+pub fn forced() -> bool {
+  env::var_os(FORCE_ENV).is_some()
+}
+
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Step {
+  name: String,
+  inputs: Vec<Input>,
+  outputs: Vec<PathBuf>,
+  args: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+enum Input {
+  Path(PathBuf),
+  Walk(Walk),
+}
+
+impl Step {
+  /// `name` identifies the step in the cache so it must be unique and a valid
+  /// file name
+  pub fn new(name: impl Into<String>) -> Self {
+    Self {
+      name: name.into(),
+      inputs: Vec::new(),
+      outputs: Vec::new(),
+      args: Vec::new(),
+    }
+  }
+
+  /// A file the step reads, or a directory whose files it reads
+  pub fn input(mut self, path: impl Into<PathBuf>) -> Self {
+    self.inputs.push(Input::Path(path.into()));
+    self
+  }
+
+  /// The files matched by `walk`
+  pub fn inputs(mut self, walk: Walk) -> Self {
+    self.inputs.push(Input::Walk(walk));
+    self
+  }
+
+  /// A file or directory the step creates. The step runs if it is missing.
+  pub fn output(mut self, path: impl Into<PathBuf>) -> Self {
+    self.outputs.push(path.into());
+    self
+  }
+
+  /// Something besides the inputs that changes what the step does, e.g. its
+  /// command line or a flag of the task
+  pub fn arg(mut self, arg: impl Display) -> Self {
+    self.args.push(arg.to_string());
+    self
+  }
+
+  /// Whether the step would be skipped
+  pub fn is_fresh(&self) -> Result<bool, AnyErr> {
+    let fingerprint = self.fingerprint()?;
+    self.is_fresh_with(&self.cache_file()?, &fingerprint)
+  }
+
+  /// Call `run` unless the step is up to date. Returns whether it ran.
+  #[track_caller]
+  pub fn run<E: Into<AnyErr>>(
+    &self,
+    run: impl FnOnce() -> Result<(), E>,
+  ) -> Result<bool, AnyErr> {
+    let cache = self.cache_file()?;
+    let fingerprint = self.fingerprint()?;
+    if self.is_fresh_with(&cache, &fingerprint)? {
+      timings::record(&self.name, Duration::ZERO, Status::Skipped);
+      return Ok(false);
+    }
+
+    // a failed run may leave the outputs half written
+    if cache.exists() {
+      fs::remove_file(&cache)?;
+    }
+    run().map_err(Into::into)?;
+    if let Some(dir) = cache.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    fs::write(&cache, fingerprint)?;
+    Ok(true)
+  }
+
+  /// Wait for `pipe` unless the step is up to date. The pipe's command line
+  /// is part of the fingerprint. Returns whether it ran.
+  #[cfg(feature = "script")]
+  #[track_caller]
+  pub fn pipe(&self, pipe: Pipe<'_>) -> Result<bool, AnyErr> {
+    self.clone().arg(&pipe).run(|| pipe.wait())
+  }
+
+  fn is_fresh_with(
+    &self,
+    cache: &Path,
+    fingerprint: &str,
+  ) -> Result<bool, AnyErr> {
+    if forced() || !self.outputs.iter().all(|output| output.exists()) {
+      return Ok(false);
+    }
+    Ok(cache.exists() && fs::read_to_string(cache)? == fingerprint)
+  }
+
+  fn cache_file(&self) -> Result<PathBuf, AnyErr> {
+    Ok(
+      workspace::metadata()?
+        .target_directory
+        .join("xtask-cache")
+        .join(&self.name),
+    )
+  }
+
+  /// A hash of the args and of the path and contents of every input file
+  fn fingerprint(&self) -> Result<String, AnyErr> {
+    let mut files = Vec::new();
+    for input in &self.inputs {
+      match input {
+        Input::Path(path) if path.is_dir() => {
+          files.extend(Walk::new(path).paths()?)
+        }
+        Input::Path(path) => files.push(path.clone()),
+        Input::Walk(walk) => files.extend(walk.paths()?),
+      }
+    }
+    files.sort();
+    files.dedup();
+
+    let mut hasher = Fnv::new();
+    for arg in &self.args {
+      hasher.write(arg.as_bytes());
+      hasher.write(&[0]);
+    }
+    for file in files {
+      hasher.write(file.to_string_lossy().as_bytes());
+      hasher.write(&[0]);
+      let contents = fs::read(&file)?;
+      hasher.write(&(contents.len() as u64).to_le_bytes());
+      hasher.write(&contents);
+    }
+    Ok(format!("{:016x}", hasher.0))
+  }
+}
+
+/// 64 bit FNV-1a, since the std hasher may change between releases and the
+/// fingerprints are kept between runs
+struct Fnv(u64);
+
+impl Fnv {
+  fn new() -> Self {
+    Self(0xcbf2_9ce4_8422_2325)
+  }
+
+  fn write(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+  }
+}
@@ -0,0 +1,373 @@
+use crate::{
+  exit,
+  timings::{self, Status, TaskTiming, TIMINGS_ENV},
+};
+use clap::{Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::{
+  env,
+  ffi::OsString,
+  fs,
+  path::PathBuf,
+  process::{self, ExitStatus},
+  time::Instant,
+};
+
+#[cfg(feature = "completions")]
+mod completions;
+mod list;
+
+/// Set on the child processes spawned to run chained tasks so that they
+/// treat their arguments as a single task
+const NO_CHAIN_ENV: &str = "XTASK_UTILS_NO_CHAIN";
+
+/// Implemented for the `Task` enum generated by [`tasks!`](crate::tasks)
+pub trait Tasks: Parser {
+  fn run(self) -> !;
+}
+
+// Options that apply to every task in the invocation. They must come before
+// the first task. Not a doc comment since clap would use it as the about text.
+#[derive(Args, Debug)]
+struct GlobalOpts {
+  /// Print how long each task and each command it ran took
+  #[arg(long)]
+  timings: bool,
+
+  /// Write the timings as JSON to this file
+  #[arg(long, value_name = "PATH")]
+  timings_json: Option<PathBuf>,
+
+  /// Run every step, even the ones that are up to date
+  #[cfg(feature = "step")]
+  #[arg(long)]
+  force: bool,
+
+  /// Answer yes to every prompt and use the defaults of the others
+  #[cfg(feature = "prompt")]
+  #[arg(long)]
+  yes: bool,
+}
+
+/// Commands provided by this crate. A task with the same name takes
+/// precedence over a builtin.
+#[derive(Subcommand, Debug)]
+enum Builtin {
+  /// List every task with its description and arguments
+  List {
+    /// Print the list as JSON
+    #[arg(long)]
+    json: bool,
+  },
+
+  /// Print a shell completion script for this xtask
+  #[cfg(feature = "completions")]
+  Completions {
+    /// The shell to generate the completions for
+    shell: clap_complete::Shell,
+
+    /// The name the xtask is invoked as [default: this executable's name]
+    #[arg(long)]
+    bin_name: Option<String>,
+  },
+
+  /// Print a man page for this xtask
+  #[cfg(feature = "completions")]
+  Man {
+    /// The name the xtask is invoked as [default: this executable's name]
+    #[arg(long)]
+    bin_name: Option<String>,
+  },
+
+  /// Rerun tasks whenever a file changes
+  #[cfg(feature = "watch")]
+  Watch {
+    /// A file or directory to watch [default: the workspace root]
+    #[arg(long = "path", value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// The tasks to run, as they would be passed to this xtask
+    #[arg(
+      required = true,
+      trailing_var_arg = true,
+      allow_hyphen_values = true
+    )]
+    tasks: Vec<OsString>,
+  },
+}
+
+impl Builtin {
+  fn run<T: CommandFactory>(self) -> ! {
+    match self {
+      Builtin::List { json } => list::print::<T>(json),
+      #[cfg(feature = "completions")]
+      Builtin::Completions { shell, bin_name } => {
+        let bin_name = bin_name.unwrap_or_else(default_bin_name);
+        completions::print(full_command::<T>(), shell, bin_name)
+      }
+      #[cfg(feature = "completions")]
+      Builtin::Man { bin_name } => {
+        let bin_name = bin_name.unwrap_or_else(default_bin_name);
+        completions::print_man(full_command::<T>(), bin_name)
+      }
+      #[cfg(feature = "watch")]
+      Builtin::Watch { paths, tasks } => watch(paths, tasks),
+    }
+    process::exit(0);
+  }
+}
+
+/// Parse one or more tasks from the command line and run them in order.
+///
+/// Tasks can be chained by name (`cargo xtask fmt lint test`) or, if they
+/// need arguments, separated with `--` (`cargo xtask fmt --check -- test`).
+/// Each task runs in its own child process, the chain stops at the first
+/// task that fails and a summary of the tasks that ran is printed to stderr.
+pub fn run_all<T: Tasks>() -> ! {
+  let mut args = env::args_os();
+  let bin = args.next().unwrap_or_else(|| "xtask".into());
+  let args: Vec<OsString> = args.collect();
+
+  if env::var_os(NO_CHAIN_ENV).is_some() {
+    // so that a nested `cargo xtask` run by the task chains as usual
+    env::remove_var(NO_CHAIN_ENV);
+    T::parse_from(Some(bin).into_iter().chain(args)).run();
+  }
+
+  let Some((globals, tasks)) = split_globals(&bin, &args) else {
+    // let clap report the error or print the help, with the globals included
+    let matches =
+      full_command::<T>().get_matches_from(Some(bin).into_iter().chain(args));
+    T::from_arg_matches(&matches)
+      .unwrap_or_else(|err| err.exit())
+      .run();
+  };
+  // the tasks may run in this process or in child processes
+  #[cfg(feature = "step")]
+  if globals.force {
+    env::set_var(crate::step::FORCE_ENV, "1");
+  }
+  #[cfg(feature = "prompt")]
+  if globals.yes {
+    env::set_var(crate::prompt::YES_ENV, "1");
+  }
+
+  if let Some(builtin) = tasks.first().and_then(|name| builtin::<T>(name)) {
+    let matches =
+      builtin.get_matches_from(Some(&bin).into_iter().chain(&tasks));
+    Builtin::from_arg_matches(&matches)
+      .unwrap_or_else(|err| err.exit())
+      .run::<T>();
+  }
+
+  let mut groups = split_tasks::<T>(&bin, tasks);
+  let timed = globals.timings || globals.timings_json.is_some();
+  if groups.len() == 1 && !timed {
+    let group = groups.pop().unwrap();
+    T::parse_from(Some(bin).into_iter().chain(group)).run();
+  }
+
+  // report typos before running anything
+  for group in &groups {
+    if let Err(err) = T::try_parse_from(Some(&bin).into_iter().chain(group)) {
+      err.exit();
+    }
+  }
+
+  let exe = env::current_exe().unwrap_or_else(|err| {
+    eprintln!("failed to locate the current executable: {err}");
+    process::exit(1);
+  });
+  let scratch = timed.then(|| {
+    timings::scratch_file().unwrap_or_else(|err| {
+      eprintln!("failed to create the timings file: {err}");
+      process::exit(1);
+    })
+  });
+
+  let mut report = Vec::with_capacity(groups.len());
+  let mut failure: Option<ExitStatus> = None;
+  for group in &groups {
+    let name = group[0].to_string_lossy().into_owned();
+    if failure.is_some() {
+      report.push(TaskTiming {
+        name,
+        duration: None,
+        status: Status::Skipped,
+        steps: Vec::new(),
+      });
+      continue;
+    }
+
+    let mut command = process::Command::new(&exe);
+    command.args(group).env(NO_CHAIN_ENV, "1");
+    if let Some(scratch) = &scratch {
+      command.env(TIMINGS_ENV, scratch);
+    }
+
+    let start = Instant::now();
+    let status = command.status().unwrap_or_else(|err| {
+      eprintln!("failed spawning {}: {err}", exe.display());
+      process::exit(1);
+    });
+    let duration = start.elapsed();
+
+    if !status.success() {
+      failure = Some(status);
+    }
+    report.push(TaskTiming {
+      name,
+      duration: Some(duration),
+      status: if status.success() {
+        Status::Ok
+      } else {
+        Status::Failed
+      },
+      steps: scratch
+        .as_deref()
+        .map(timings::take_steps)
+        .unwrap_or_default(),
+    });
+  }
+
+  timings::print_table(&report);
+  if let Some(scratch) = scratch {
+    fs::remove_file(scratch).ok();
+  }
+  if let Some(path) = &globals.timings_json {
+    if let Err(err) = timings::write_json(path, &report) {
+      eprintln!("failed writing timings to {}: {err}", path.display());
+    }
+  }
+
+  match failure {
+    Some(status) => process::exit(exit::status_code(&status)),
+    None => process::exit(0),
+  }
+}
+
+/// Run the tasks in a child process, restarting it whenever a file changes
+#[cfg(feature = "watch")]
+fn watch(paths: Vec<PathBuf>, tasks: Vec<OsString>) -> ! {
+  use crate::{result_ext::ErrorExt, watch::Watch};
+
+  let exe = env::current_exe().unwrap_or_else(|err| {
+    eprintln!("failed to locate the current executable: {err}");
+    process::exit(1);
+  });
+  let mut command = process::Command::new(exe);
+  command.args(tasks);
+
+  let watch = paths.into_iter().fold(Watch::new(), Watch::path);
+  watch.run_command(&mut command).exit()
+}
+
+/// The command used for help and errors, which includes the global options
+/// and the builtins
+fn full_command<T: CommandFactory>() -> Command {
+  let mut command = GlobalOpts::augment_args(T::command());
+  let builtins = Builtin::augment_subcommands(Command::new("builtins"));
+  for builtin in builtins.get_subcommands() {
+    if command.find_subcommand(builtin.get_name()).is_none() {
+      command = command.subcommand(builtin.clone().display_order(usize::MAX));
+    }
+  }
+  command
+}
+
+/// The file name of this executable, without any extension
+#[cfg(feature = "completions")]
+fn default_bin_name() -> String {
+  let bin = env::args_os().next().unwrap_or_else(|| "xtask".into());
+  std::path::Path::new(&bin)
+    .file_stem()
+    .unwrap_or(&bin)
+    .to_string_lossy()
+    .into_owned()
+}
+
+/// A command that parses the builtin called `name`, unless there is no such
+/// builtin or it is shadowed by a task
+fn builtin<T: CommandFactory>(name: &OsString) -> Option<Command> {
+  let name = name.to_str()?;
+  let command = Builtin::augment_subcommands(Command::new("builtins"));
+  if command.find_subcommand(name).is_none()
+    || T::command().find_subcommand(name).is_some()
+  {
+    return None;
+  }
+  Some(command.subcommand_required(true))
+}
+
+/// Separate the leading global options from the tasks. Returns `None` if the
+/// arguments can't be parsed this way.
+fn split_globals(
+  bin: &OsString,
+  args: &[OsString],
+) -> Option<(GlobalOpts, Vec<OsString>)> {
+  // errors from this command are never shown so its name doesn't matter
+  let command = GlobalOpts::augment_args(Command::new("xtask"))
+    .allow_external_subcommands(true)
+    .external_subcommand_value_parser(clap::value_parser!(OsString))
+    .disable_help_flag(true)
+    .disable_version_flag(true);
+  let matches = command
+    .try_get_matches_from(Some(bin).into_iter().chain(args))
+    .ok()?;
+  let globals = GlobalOpts::from_arg_matches(&matches).ok()?;
+
+  let tasks = match matches.subcommand() {
+    Some((name, sub_matches)) => Some(OsString::from(name))
+      .into_iter()
+      .chain(
+        sub_matches
+          .get_many::<OsString>("")
+          .into_iter()
+          .flatten()
+          .cloned(),
+      )
+      .collect(),
+    None => return None,
+  };
+  Some((globals, tasks))
+}
+
+/// Split the arguments into one group per task. A `--` only starts a new task
+/// if a task name follows it, otherwise it is passed to the current one.
+/// Without any `--` the arguments are only treated as several tasks if they
+/// are all task names and don't parse as a single task.
+fn split_tasks<T: Parser>(
+  bin: &OsString,
+  args: Vec<OsString>,
+) -> Vec<Vec<OsString>> {
+  let command = T::command();
+  let is_task = |arg: &OsString| {
+    arg
+      .to_str()
+      .is_some_and(|arg| command.find_subcommand(arg).is_some())
+  };
+
+  let mut groups = vec![Vec::new()];
+  let mut args = args.into_iter().peekable();
+  while let Some(arg) = args.next() {
+    if arg == "--" && args.peek().is_some_and(is_task) {
+      groups.push(Vec::new());
+    } else {
+      groups.last_mut().unwrap().push(arg);
+    }
+  }
+  groups.retain(|group| !group.is_empty());
+
+  if groups.len() == 1
+    && groups[0].len() > 1
+    && groups[0].iter().all(is_task)
+    && T::try_parse_from(Some(bin).into_iter().chain(&groups[0])).is_err()
+  {
+    let group = groups.pop().unwrap();
+    return group.into_iter().map(|arg| vec![arg]).collect();
+  }
+
+  if groups.is_empty() {
+    groups.push(Vec::new());
+  }
+  groups
+}
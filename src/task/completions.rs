@@ -0,0 +1,16 @@
+use clap::Command;
+use clap_complete::Shell;
+use clap_mangen::Man;
+use std::{io, process};
+
+pub(super) fn print(mut command: Command, shell: Shell, bin_name: String) {
+  clap_complete::generate(shell, &mut command, bin_name, &mut io::stdout());
+}
+
+pub(super) fn print_man(command: Command, bin_name: String) {
+  let command = command.display_name(&bin_name).bin_name(bin_name);
+  if let Err(err) = Man::new(command).render(&mut io::stdout()) {
+    eprintln!("failed writing the man page: {err}");
+    process::exit(1);
+  }
+}
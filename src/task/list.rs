@@ -0,0 +1,126 @@
+use crate::json;
+use clap::{Arg, ArgAction, Command, CommandFactory};
+
+/// Print every task with its description and arguments to stdout
+pub(super) fn print<T: CommandFactory>(as_json: bool) {
+  let mut command = T::command();
+  command.build();
+  let tasks: Vec<_> = command
+    .get_subcommands()
+    .filter(|task| !task.is_hide_set() && task.get_name() != "help")
+    .collect();
+
+  if as_json {
+    println!(
+      "{{\"tasks\":{}}}",
+      json::array(tasks.into_iter().map(task_json))
+    );
+    return;
+  }
+
+  let width = tasks
+    .iter()
+    .map(|task| task.get_name().len())
+    .max()
+    .unwrap_or(0);
+  for task in tasks {
+    match task.get_about() {
+      Some(about) => println!("{:width$}  {about}", task.get_name()),
+      None => println!("{}", task.get_name()),
+    }
+
+    let args: Vec<_> = args(task).map(|arg| (usage(arg), arg)).collect();
+    let arg_width = args.iter().map(|(usage, _)| usage.len()).max();
+    for (usage, arg) in &args {
+      match arg.get_help() {
+        Some(help) => {
+          println!(
+            "    {usage:arg_width$}  {help}",
+            arg_width = arg_width.unwrap()
+          )
+        }
+        None => println!("    {usage}"),
+      }
+    }
+  }
+}
+
+fn args(task: &Command) -> impl Iterator<Item = &Arg> {
+  task.get_arguments().filter(|arg| {
+    !arg.is_hide_set()
+      && !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version)
+  })
+}
+
+fn value_names(arg: &Arg) -> Vec<String> {
+  match arg.get_value_names() {
+    Some(names) => names.iter().map(ToString::to_string).collect(),
+    None => vec![arg.get_id().as_str().to_uppercase()],
+  }
+}
+
+fn is_multiple(arg: &Arg) -> bool {
+  matches!(arg.get_action(), ArgAction::Append | ArgAction::Count)
+    || arg
+      .get_num_args()
+      .is_some_and(|range| range.max_values() > 1)
+}
+
+/// How the argument is written on the command line, e.g. `--name <NAME>`
+fn usage(arg: &Arg) -> String {
+  let names = value_names(arg);
+  let multiple = if is_multiple(arg) { "..." } else { "" };
+
+  if arg.is_positional() {
+    let names = names.join(" ");
+    return if arg.is_required_set() {
+      format!("<{names}>{multiple}")
+    } else {
+      format!("[{names}]{multiple}")
+    };
+  }
+
+  let mut usage = match (arg.get_short(), arg.get_long()) {
+    (Some(short), Some(long)) => format!("-{short}, --{long}"),
+    (Some(short), None) => format!("-{short}"),
+    (None, Some(long)) => format!("--{long}"),
+    (None, None) => String::new(),
+  };
+  if arg.get_action().takes_values() {
+    for name in names {
+      usage.push_str(&format!(" <{name}>"));
+    }
+  }
+  usage.push_str(multiple);
+  usage
+}
+
+fn task_json(task: &Command) -> String {
+  let about = optional(task.get_about().map(ToString::to_string));
+  let args = json::array(args(task).map(|arg| {
+    format!(
+      concat!(
+        r#"{{"name":{},"positional":{},"short":{},"long":{},"#,
+        r#""required":{},"multiple":{},"takes_value":{},"#,
+        r#""value_names":{},"help":{}}}"#,
+      ),
+      json::string(arg.get_id().as_str()),
+      arg.is_positional(),
+      optional(arg.get_short().map(String::from)),
+      optional(arg.get_long().map(String::from)),
+      arg.is_required_set(),
+      is_multiple(arg),
+      arg.get_action().takes_values(),
+      json::array(value_names(arg).iter().map(|name| json::string(name))),
+      optional(arg.get_help().map(ToString::to_string)),
+    )
+  }));
+  format!(
+    r#"{{"name":{},"about":{about},"args":{args}}}"#,
+    json::string(task.get_name())
+  )
+}
+
+fn optional(s: Option<String>) -> String {
+  s.map_or_else(|| String::from("null"), |s| json::string(&s))
+}
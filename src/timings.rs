@@ -0,0 +1,205 @@
+//! Wall-clock timings of tasks and the commands they run.
+//!
+//! Timings are only collected when the xtask is run with `--timings` or
+//! `--timings-json`. The process running the tasks sets an environment
+//! variable pointing at a scratch file and every step, in any child process,
+//! appends a line to it.
+
+use crate::json;
+use std::{
+  env,
+  fmt::{self, Display, Formatter},
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+pub(crate) const TIMINGS_ENV: &str = "XTASK_UTILS_TIMINGS";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+  Ok,
+  Failed,
+  Skipped,
+}
+
+impl Status {
+  fn as_str(self) -> &'static str {
+    match self {
+      Status::Ok => "ok",
+      Status::Failed => "failed",
+      Status::Skipped => "skipped",
+    }
+  }
+
+  fn parse(s: &str) -> Option<Self> {
+    match s {
+      "ok" => Some(Status::Ok),
+      "failed" => Some(Status::Failed),
+      "skipped" => Some(Status::Skipped),
+      _ => None,
+    }
+  }
+}
+
+impl Display for Status {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.pad(self.as_str())
+  }
+}
+
+/// Whether timings are being collected
+pub fn enabled() -> bool {
+  env::var_os(TIMINGS_ENV).is_some()
+}
+
+/// Record how long a step took. Does nothing unless timings are enabled.
+pub fn record(name: &str, duration: Duration, status: Status) {
+  let Some(path) = env::var_os(TIMINGS_ENV) else {
+    return;
+  };
+  let name = name.replace(['\t', '\n', '\r'], " ");
+  let line = format!("{name}\t{}\t{status}\n", duration.as_micros());
+  // timings are best effort, they should never fail the task
+  OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)
+    .and_then(|mut file| file.write_all(line.as_bytes()))
+    .ok();
+}
+
+/// Times a step from its creation until [`Timer::finish`] is called
+pub struct Timer {
+  name: String,
+  start: Instant,
+}
+
+impl Timer {
+  /// Start timing a step, or return `None` if timings are not enabled
+  pub fn start(name: impl ToString) -> Option<Self> {
+    enabled().then(|| Self {
+      name: name.to_string(),
+      start: Instant::now(),
+    })
+  }
+
+  pub fn finish(self, status: Status) {
+    record(&self.name, self.start.elapsed(), status);
+  }
+}
+
+pub(crate) struct TaskTiming {
+  pub(crate) name: String,
+  pub(crate) duration: Option<Duration>,
+  pub(crate) status: Status,
+  pub(crate) steps: Vec<StepTiming>,
+}
+
+pub(crate) struct StepTiming {
+  name: String,
+  duration: Duration,
+  status: Status,
+}
+
+/// Take the steps recorded in the scratch file so far, leaving it empty
+pub(crate) fn take_steps(path: &Path) -> Vec<StepTiming> {
+  let steps = match fs::read_to_string(path) {
+    Ok(contents) => contents.lines().filter_map(parse_step).collect(),
+    Err(_) => return Vec::new(),
+  };
+  fs::write(path, "").ok();
+  steps
+}
+
+fn parse_step(line: &str) -> Option<StepTiming> {
+  let mut parts = line.rsplitn(3, '\t');
+  let status = Status::parse(parts.next()?)?;
+  let duration = Duration::from_micros(parts.next()?.parse().ok()?);
+  let name = parts.next()?.to_owned();
+  Some(StepTiming {
+    name,
+    duration,
+    status,
+  })
+}
+
+/// Create an empty scratch file for the steps to be recorded in. It is created
+/// with `create_new` so an existing file or symlink at the path isn't written
+/// through.
+pub(crate) fn scratch_file() -> io::Result<PathBuf> {
+  let mut attempt = 0;
+  loop {
+    let path = env::temp_dir().join(format!(
+      "{}-timings-{}-{attempt}.tsv",
+      env!("CARGO_PKG_NAME"),
+      std::process::id()
+    ));
+    match OpenOptions::new().write(true).create_new(true).open(&path) {
+      Ok(_) => return Ok(path),
+      Err(err)
+        if err.kind() == io::ErrorKind::AlreadyExists && attempt < 100 =>
+      {
+        attempt += 1;
+      }
+      Err(err) => return Err(err),
+    }
+  }
+}
+
+pub(crate) fn print_table(tasks: &[TaskTiming]) {
+  let rows: Vec<_> = tasks
+    .iter()
+    .flat_map(|task| {
+      Some((task.name.clone(), task.duration, task.status))
+        .into_iter()
+        .chain(task.steps.iter().map(|step| {
+          (format!("  {}", step.name), Some(step.duration), step.status)
+        }))
+    })
+    .collect();
+  let width = rows
+    .iter()
+    .map(|(name, ..)| name.chars().count())
+    .max()
+    .unwrap_or(0)
+    .max("step".len());
+
+  eprintln!("\n--------------------------------------");
+  eprintln!("  {:width$}  {:>10}  status", "step", "duration");
+  for (name, duration, status) in rows {
+    let duration = match duration {
+      Some(duration) => format!("{duration:.2?}"),
+      None => String::from("-"),
+    };
+    eprintln!("  {name:width$}  {duration:>10}  {status}");
+  }
+}
+
+pub(crate) fn write_json(path: &Path, tasks: &[TaskTiming]) -> io::Result<()> {
+  let tasks = json::array(tasks.iter().map(|task| {
+    let steps = json::array(task.steps.iter().map(|step| {
+      format!(
+        r#"{{"name":{},"duration_secs":{},"status":"{}"}}"#,
+        json::string(&step.name),
+        step.duration.as_secs_f64(),
+        step.status,
+      )
+    }));
+    let duration = match task.duration {
+      Some(duration) => duration.as_secs_f64().to_string(),
+      None => String::from("null"),
+    };
+    format!(
+      r#"{{"name":{},"duration_secs":{duration},"status":"{}","steps":{steps}}}"#,
+      json::string(&task.name),
+      task.status,
+    )
+  }));
+
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  fs::write(path, format!("{{\"tasks\":{tasks}}}\n"))
+}
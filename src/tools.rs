@@ -0,0 +1,270 @@
+//! Check that the tools a task runs are installed before it starts.
+//!
+//! Without a check a missing tool only shows up as a spawn error once the
+//! task gets to it, possibly after a long build. [`check`] reports every
+//! missing or outdated tool at once, with a hint on how to install it.
+//!
+//! Tools that are cargo packages can instead be pinned with
+//! [`Tool::cargo_install`], so they are installed into the workspace's target
+//! directory when missing.
+//!
+//! ```no_run
+//! # use xtask_utils::{prelude::*, tools::{self, require_tool}};
+//! tools::check([
+//!   require_tool("protoc").hint("install it with your package manager"),
+//!   require_tool("wasm-pack")
+//!     .version(">=0.12")
+//!     .hint("cargo install wasm-pack"),
+//!   require_tool("cargo-nextest")
+//!     .version_args(["nextest", "--version"])
+//!     .cargo_install("cargo-nextest", "0.9.72"),
+//! ])
+//! .exit_on_err();
+//! ```
+
+use crate::{
+  any_err::{AnyErr, Errors},
+  command_ext::CommandExt,
+  result_ext::ResultExt,
+  workspace,
+};
+use semver::{Version, VersionReq};
+use std::{
+  env,
+  ffi::{OsStr, OsString},
+  path::{Path, PathBuf},
+  process::Command,
+  sync::OnceLock,
+};
+
+/// A tool that must be on the `PATH`
+pub fn require_tool(name: impl Into<String>) -> Tool {
+  Tool {
+    name: name.into(),
+    version: None,
+    version_args: vec!["--version".into()],
+    hint: None,
+    install: None,
+  }
+}
+
+/// Check every tool, failing with an error for each one that is missing or
+/// has the wrong version
+pub fn check(tools: impl IntoIterator<Item = Tool>) -> Result<(), Errors> {
+  let mut errors = Errors::with_message("required tools are missing");
+  for tool in tools {
+    if let Err(err) = tool.find() {
+      errors.push(err);
+    }
+  }
+  errors.into_result(())
+}
+
+/// Where [`Tool::cargo_install`] installs tools, `xtask-tools` in the target
+/// directory. The first call adds its `bin` directory to the front of the
+/// `PATH`, so the commands run by the rest of the process use those tools.
+pub fn install_root() -> Result<&'static Path, AnyErr> {
+  static ROOT: OnceLock<PathBuf> = OnceLock::new();
+  if let Some(root) = ROOT.get() {
+    return Ok(root);
+  }
+
+  let root = workspace::metadata()?.target_directory.join("xtask-tools");
+  let paths = env::var_os("PATH").unwrap_or_default();
+  let paths = env::join_paths(
+    [root.join("bin")]
+      .into_iter()
+      .chain(env::split_paths(&paths)),
+  )
+  .context("the target directory can't be added to the PATH")?;
+  Ok(ROOT.get_or_init(|| {
+    env::set_var("PATH", paths);
+    root
+  }))
+}
+
+/// The path of the executable `name` would run, searching the `PATH` unless
+/// it contains a path separator
+pub fn which(name: impl AsRef<OsStr>) -> Option<PathBuf> {
+  let name = Path::new(name.as_ref());
+  if name.components().count() > 1 {
+    return is_executable(name).then(|| name.to_owned());
+  }
+  env::split_paths(&env::var_os("PATH")?).find_map(|dir| {
+    let mut path = dir.join(name).into_os_string();
+    path.push(env::consts::EXE_SUFFIX);
+    let path = PathBuf::from(path);
+    is_executable(&path).then_some(path)
+  })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+  use std::os::unix::fs::PermissionsExt;
+
+  path
+    .metadata()
+    .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+  path.is_file()
+}
+
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Tool {
+  name: String,
+  version: Option<String>,
+  version_args: Vec<OsString>,
+  hint: Option<String>,
+  install: Option<(String, String)>,
+}
+
+impl Tool {
+  /// Require a version matching `req`, e.g. `>=1.2` or `=0.9.72`. A bare
+  /// version like `1.2` means `^1.2`, as in `Cargo.toml`.
+  pub fn version(mut self, req: impl Into<String>) -> Self {
+    self.version = Some(req.into());
+    self
+  }
+
+  /// The arguments that make the tool print its version, `--version` by
+  /// default. Cargo subcommands need their name first, e.g.
+  /// `["nextest", "--version"]` for `cargo-nextest`.
+  pub fn version_args<I, S>(mut self, args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+  {
+    self.version_args = args.into_iter().map(Into::into).collect();
+    self
+  }
+
+  /// How to install the tool, shown if it is missing or outdated
+  pub fn hint(mut self, hint: impl Into<String>) -> Self {
+    self.hint = Some(hint.into());
+    self
+  }
+
+  /// Install `version` of the package `krate` with `cargo install` if the
+  /// tool is missing or outdated. It is installed into [`install_root`], and
+  /// without a [`version`](Self::version) exactly `version` is required.
+  ///
+  /// `cargo install` uses the registry configured for cargo, so this works
+  /// offline with a local registry or vendored sources.
+  pub fn cargo_install(
+    mut self,
+    krate: impl Into<String>,
+    version: impl Into<String>,
+  ) -> Self {
+    let version = version.into();
+    self.version.get_or_insert_with(|| format!("={version}"));
+    self.install = Some((krate.into(), version));
+    self
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Find the tool and check its version, returning its path. Tools with
+  /// [`cargo_install`](Self::cargo_install) are installed if needed.
+  #[track_caller]
+  pub fn find(&self) -> Result<PathBuf, AnyErr> {
+    let res = match &self.install {
+      Some((krate, version)) => install_root().and_then(|root| {
+        self
+          .find_inner()
+          .or_else(|_| self.install(root, krate, version))
+      }),
+      None => self.find_inner(),
+    };
+    res.map_err(|err| match &self.hint {
+      Some(hint) => err.hint(hint),
+      None => err,
+    })
+  }
+
+  #[track_caller]
+  fn install(
+    &self,
+    root: &Path,
+    krate: &str,
+    version: &str,
+  ) -> Result<PathBuf, AnyErr> {
+    workspace::cargo()
+      .args(["install", "--root"])
+      .arg(root)
+      .args(["--version", version, krate])
+      .read()
+      .context(format!("failed to install {krate} {version}"))?;
+    self.find_inner()
+  }
+
+  #[track_caller]
+  fn find_inner(&self) -> Result<PathBuf, AnyErr> {
+    let Some(path) = which(&self.name) else {
+      return Err(AnyErr::new(format!(
+        "`{}` was not found on the PATH",
+        self.name
+      )));
+    };
+    let Some(req) = &self.version else {
+      return Ok(path);
+    };
+    let req = VersionReq::parse(req)
+      .context(format!("invalid version requirement `{req}`"))?;
+
+    let version = self.installed_version(&path)?;
+    if !req.matches(&version) {
+      return Err(
+        AnyErr::new(format!(
+          "`{}` {version} is installed but {req} is required",
+          self.name
+        ))
+        .field("path", path.display()),
+      );
+    }
+    Ok(path)
+  }
+
+  #[track_caller]
+  fn installed_version(&self, path: &Path) -> Result<Version, AnyErr> {
+    let output = Command::new(path)
+      .args(&self.version_args)
+      .output()
+      .context(format!("failed to run `{}`", self.name))?;
+    if !output.status.success() {
+      return Err(
+        AnyErr::new(format!("failed to get the version of `{}`", self.name))
+          .field("status", output.status),
+      );
+    }
+
+    // some tools print their version to stderr
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    parse_version(&stdout)
+      .or_else(|| parse_version(&stderr))
+      .ok_or_else(|| {
+        AnyErr::new(format!("no version in the output of `{}`", self.name))
+          .field("output", stdout.lines().next().unwrap_or_default())
+      })
+  }
+}
+
+/// The first version in `output`, e.g. `1.2.3` in `tool v1.2.3-beta`. A
+/// missing patch version is taken to be 0.
+fn parse_version(output: &str) -> Option<Version> {
+  output
+    .split(|c: char| !c.is_ascii_digit() && c != '.')
+    .find_map(|word| {
+      let mut parts = word.split('.').map(str::parse);
+      let major = parts.next()?.ok()?;
+      let minor = parts.next()?.ok()?;
+      let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+      Some(Version::new(major, minor, patch))
+    })
+}
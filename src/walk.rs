@@ -0,0 +1,113 @@
+//! Find the files a task should work on.
+//!
+//! ```no_run
+//! # use xtask_utils::walk::Walk;
+//! // every rust file under `crates/`, except the ones in `target/`
+//! let files = Walk::new("crates")
+//!   .include("*.rs")
+//!   .exclude("target/")
+//!   .paths()
+//!   .unwrap();
+//! ```
+
+use crate::{any_err::AnyErr, result_ext::ResultExt};
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use std::{ffi::OsStr, path::PathBuf, process::Command};
+
+/// Walks a directory tree for the files matching some glob patterns.
+///
+/// The patterns are matched against paths relative to the root and use
+/// `.gitignore` syntax, so `*.rs` matches in any directory while `/build.rs`
+/// only matches at the root. Files ignored by `.gitignore` and hidden files
+/// are skipped unless enabled.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Walk {
+  root: PathBuf,
+  include: Vec<String>,
+  exclude: Vec<String>,
+  gitignore: bool,
+  hidden: bool,
+}
+
+impl Walk {
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    Self {
+      root: root.into(),
+      include: Vec::new(),
+      exclude: Vec::new(),
+      gitignore: true,
+      hidden: false,
+    }
+  }
+
+  /// Only yield files matching `pattern`, or any other included pattern. If
+  /// no pattern is included every file is.
+  pub fn include(mut self, pattern: impl Into<String>) -> Self {
+    self.include.push(pattern.into());
+    self
+  }
+
+  /// Skip the files and directories matching `pattern`
+  pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+    self.exclude.push(pattern.into());
+    self
+  }
+
+  /// Whether to skip the files ignored by `.gitignore` and similar files.
+  /// Enabled by default.
+  pub fn gitignore(mut self, enabled: bool) -> Self {
+    self.gitignore = enabled;
+    self
+  }
+
+  /// Whether to include hidden files and directories. Disabled by default.
+  pub fn hidden(mut self, enabled: bool) -> Self {
+    self.hidden = enabled;
+    self
+  }
+
+  /// Every matching file, sorted by path
+  #[track_caller]
+  pub fn paths(&self) -> Result<Vec<PathBuf>, AnyErr> {
+    let mut overrides = OverrideBuilder::new(&self.root);
+    for pattern in &self.include {
+      overrides
+        .add(pattern)
+        .context(format!("invalid include pattern `{pattern}`"))?;
+    }
+    for pattern in &self.exclude {
+      overrides
+        .add(&format!("!{pattern}"))
+        .context(format!("invalid exclude pattern `{pattern}`"))?;
+    }
+    let overrides = overrides.build().context("invalid patterns")?;
+
+    let walker = WalkBuilder::new(&self.root)
+      .standard_filters(self.gitignore)
+      .hidden(!self.hidden)
+      .require_git(false)
+      .overrides(overrides)
+      .build();
+
+    let mut paths = Vec::new();
+    for entry in walker {
+      let entry =
+        entry.context(format!("failed to walk {}", self.root.display()))?;
+      if entry.file_type().is_some_and(|ty| ty.is_file()) {
+        paths.push(entry.into_path());
+      }
+    }
+    paths.sort();
+    Ok(paths)
+  }
+
+  /// A command running `program` with every matching file as an argument,
+  /// e.g. to use as a section of a `Pipe`
+  #[track_caller]
+  pub fn command(&self, program: impl AsRef<OsStr>) -> Result<Command, AnyErr> {
+    let mut command = Command::new(program);
+    command.args(self.paths()?);
+    Ok(command)
+  }
+}
@@ -0,0 +1,371 @@
+//! Rerun a command whenever files change.
+//!
+//! Files are watched with the native API of the platform, e.g. inotify on
+//! Linux, falling back to polling if it isn't available. Changes to files
+//! ignored by `.gitignore`, in `.git` or in `target` are ignored. The
+//! builtin `watch` command of [`tasks!`](crate::tasks) reruns any task this
+//! way, e.g. `cargo xtask watch test`.
+//!
+//! On Unix [`Watch::run_command`] spawns the command in its own process
+//! group, so that cancelling a run also stops the processes it started.
+//!
+//! ```no_run
+//! # use xtask_utils::{cmd, watch::Watch};
+//! let err = Watch::new().path("src").run(|| cmd!("cargo check").spawn());
+//! ```
+
+use crate::{
+  any_err::AnyErr,
+  command_ext,
+  pipe::JoinHandle,
+  result_ext::{report, ErrorExt, ResultExt},
+  workspace,
+};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{
+  Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode,
+  Watcher,
+};
+use std::{
+  env,
+  path::{Path, PathBuf},
+  process::Command,
+  sync::mpsc::{self, Receiver, RecvTimeoutError},
+  time::Duration,
+};
+#[cfg(unix)]
+use {
+  rustix::{
+    io::Errno,
+    process::{kill_process_group, Pid, Signal},
+  },
+  std::sync::{Mutex, MutexGuard, PoisonError},
+};
+
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Watch {
+  paths: Vec<PathBuf>,
+  exclude: Vec<String>,
+  debounce: Duration,
+  poll: Option<Duration>,
+}
+
+impl Default for Watch {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Watch {
+  pub fn new() -> Self {
+    Self {
+      paths: Vec::new(),
+      exclude: Vec::new(),
+      debounce: Duration::from_millis(200),
+      poll: None,
+    }
+  }
+
+  /// A file or directory to watch. The workspace root is watched if no path
+  /// is given.
+  pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+    self.paths.push(path.into());
+    self
+  }
+
+  /// Ignore changes to the files and directories matching `pattern`, which
+  /// uses `.gitignore` syntax
+  pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+    self.exclude.push(pattern.into());
+    self
+  }
+
+  /// How long to wait for more changes after one is seen, so that saving
+  /// several files only reruns once. 200ms by default.
+  pub fn debounce(mut self, debounce: Duration) -> Self {
+    self.debounce = debounce;
+    self
+  }
+
+  /// Poll for changes every `interval` instead of using the native API,
+  /// e.g. for network file systems that don't report changes
+  pub fn poll(mut self, interval: Duration) -> Self {
+    self.poll = Some(interval);
+    self
+  }
+
+  /// Start watching. Each item of the iterator is the files that changed
+  /// together.
+  pub fn changes(&self) -> Result<Changes, AnyErr> {
+    let roots = if self.paths.is_empty() {
+      vec![workspace::root()?.to_owned()]
+    } else {
+      let dir = env::current_dir().context("failed to get the current dir")?;
+      self.paths.iter().map(|path| dir.join(path)).collect()
+    };
+    let filters = roots
+      .iter()
+      .map(|root| self.filter(root))
+      .collect::<Result<_, _>>()?;
+
+    let (sender, events) = mpsc::channel();
+    let watcher = match self.poll {
+      Some(interval) => poll_watcher(sender, interval, &roots)?,
+      None => RecommendedWatcher::new(sender.clone(), Config::default())
+        .and_then(|watcher| watch(watcher, &roots))
+        .or_else(|_| poll_watcher(sender, Duration::from_secs(1), &roots))?,
+    };
+
+    Ok(Changes {
+      _watcher: watcher,
+      events,
+      roots,
+      filters,
+      debounce: self.debounce,
+    })
+  }
+
+  /// Run `start` and run it again whenever files change, cancelling the
+  /// previous run if it hasn't finished. Errors starting it are printed. Only
+  /// returns if watching fails.
+  ///
+  /// On Unix commands that lead their own process group are cancelled by
+  /// killing the whole group.
+  #[must_use]
+  pub fn run<F>(&self, mut start: F) -> AnyErr
+  where
+    F: FnMut() -> command_ext::Result<JoinHandle>,
+  {
+    let changes = match self.changes() {
+      Ok(changes) => changes,
+      Err(err) => return err,
+    };
+    #[cfg(unix)]
+    if let Err(err) = kill_on_signal() {
+      return err;
+    }
+    let mut start = || match start() {
+      Ok(handle) => {
+        #[cfg(unix)]
+        lock_running().extend(groups(&handle));
+        Some(handle)
+      }
+      Err(err) => {
+        report::print(&err);
+        None
+      }
+    };
+
+    let mut handle = start();
+    for paths in changes {
+      if let Some(Err(err)) = handle.take().map(cancel) {
+        report::print(&err);
+      }
+      match paths.as_slice() {
+        [path] => eprintln!("\n[watch] {} changed", path.display()),
+        paths => eprintln!("\n[watch] {} files changed", paths.len()),
+      }
+      handle = start();
+    }
+    AnyErr::new("stopped watching for changes")
+  }
+
+  /// Run `command` and run it again whenever files change, like
+  /// [`run`](Self::run). On Unix it is spawned in its own process group.
+  #[must_use]
+  pub fn run_command(&self, command: &mut Command) -> AnyErr {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    self.run(|| {
+      command
+        .spawn()
+        .map(JoinHandle::Cmd)
+        .map_err(|err| command_ext::Error {
+          name: command.get_program().to_string_lossy().into_owned(),
+          kind: command_ext::ErrorKind::Spawn(err),
+        })
+    })
+  }
+
+  fn filter(&self, root: &Path) -> Result<Gitignore, AnyErr> {
+    let mut builder = GitignoreBuilder::new(root);
+    // a missing `.gitignore` isn't an error
+    if root.join(".gitignore").is_file() {
+      if let Some(err) = builder.add(root.join(".gitignore")) {
+        return Err(err.context("failed to read .gitignore"));
+      }
+    }
+    for pattern in [".git/", "target/"]
+      .into_iter()
+      .chain(self.exclude.iter().map(String::as_str))
+    {
+      builder
+        .add_line(None, pattern)
+        .context(format!("invalid exclude pattern `{pattern}`"))?;
+    }
+    builder.build().context("invalid exclude patterns")
+  }
+}
+
+/// The process groups of the current runs, which are killed if the watcher
+/// is interrupted since Ctrl-C doesn't reach them. A finished command isn't
+/// reaped until it is cancelled, so its id can't be reused while it is here.
+#[cfg(unix)]
+static RUNNING: Mutex<Vec<Pid>> = Mutex::new(Vec::new());
+
+#[cfg(unix)]
+fn lock_running() -> MutexGuard<'static, Vec<Pid>> {
+  RUNNING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Kill the commands of `handle` and the processes they started, if they
+/// lead a process group, and wait for them to exit
+fn cancel(handle: JoinHandle) -> Result<(), AnyErr> {
+  #[cfg(unix)]
+  {
+    // hold the lock until they are reaped so a signal can't kill the groups
+    // of reused ids
+    let mut running = lock_running();
+    let groups = groups(&handle);
+    running.retain(|pid| !groups.contains(pid));
+    let killed = kill_groups(&groups);
+    handle.cancel();
+    killed
+  }
+  #[cfg(not(unix))]
+  {
+    handle.cancel();
+    Ok(())
+  }
+}
+
+#[cfg(unix)]
+fn groups(handle: &JoinHandle) -> Vec<Pid> {
+  match handle {
+    JoinHandle::Cmd(child) => vec![Pid::from_child(child)],
+    JoinHandle::Multiple(handles) => handles.iter().flat_map(groups).collect(),
+  }
+}
+
+/// Kill the process groups with the ids in `groups`. A process that doesn't
+/// lead a group is left alone since no group has its id.
+#[cfg(unix)]
+fn kill_groups(groups: &[Pid]) -> Result<(), AnyErr> {
+  for &group in groups {
+    match kill_process_group(group, Signal::KILL) {
+      Ok(()) | Err(Errno::SRCH) => {}
+      Err(err) => {
+        return Err(err.context(format!(
+          "failed to kill process group {}",
+          group.as_raw_nonzero()
+        )))
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Kill the process groups of the current runs and exit when the watcher is
+/// interrupted or terminated. The handlers are only registered once.
+#[cfg(unix)]
+fn kill_on_signal() -> Result<(), AnyErr> {
+  use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+  };
+  use std::{process, thread};
+
+  static REGISTERED: Mutex<bool> = Mutex::new(false);
+  let mut registered =
+    REGISTERED.lock().unwrap_or_else(PoisonError::into_inner);
+  if *registered {
+    return Ok(());
+  }
+  let mut signals = Signals::new([SIGHUP, SIGINT, SIGTERM])
+    .context("failed to handle signals")?;
+  thread::spawn(move || {
+    if let Some(signal) = signals.forever().next() {
+      // exiting anyway, so there is nothing to do about a failure
+      kill_groups(&lock_running()).ok();
+      process::exit(128 + signal);
+    }
+  });
+  *registered = true;
+  Ok(())
+}
+
+fn watch<W: Watcher + Send + 'static>(
+  mut watcher: W,
+  roots: &[PathBuf],
+) -> notify::Result<Box<dyn Watcher + Send>> {
+  for root in roots {
+    watcher.watch(root, RecursiveMode::Recursive)?;
+  }
+  Ok(Box::new(watcher))
+}
+
+fn poll_watcher(
+  sender: mpsc::Sender<notify::Result<Event>>,
+  interval: Duration,
+  roots: &[PathBuf],
+) -> Result<Box<dyn Watcher + Send>, AnyErr> {
+  let config = Config::default().with_poll_interval(interval);
+  PollWatcher::new(sender, config)
+    .and_then(|watcher| watch(watcher, roots))
+    .context("failed to watch for changes")
+}
+
+/// Changes to the watched files, see [`Watch::changes`]
+pub struct Changes {
+  _watcher: Box<dyn Watcher + Send>,
+  events: Receiver<notify::Result<Event>>,
+  roots: Vec<PathBuf>,
+  filters: Vec<Gitignore>,
+  debounce: Duration,
+}
+
+impl Changes {
+  fn relevant(&self, event: notify::Result<Event>) -> Vec<PathBuf> {
+    let Ok(event) = event else {
+      return Vec::new();
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+      return Vec::new();
+    }
+    event
+      .paths
+      .into_iter()
+      // changes to a directory's contents are reported for the files too
+      .filter(|path| !path.is_dir())
+      .filter(|path| {
+        self.roots.iter().zip(&self.filters).any(|(root, filter)| {
+          path.starts_with(root)
+            && !filter.matched_path_or_any_parents(path, false).is_ignore()
+        })
+      })
+      .collect()
+  }
+}
+
+impl Iterator for Changes {
+  type Item = Vec<PathBuf>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let mut paths = Vec::new();
+    while paths.is_empty() {
+      let event = self.events.recv().ok()?;
+      paths = self.relevant(event);
+    }
+    loop {
+      match self.events.recv_timeout(self.debounce) {
+        Ok(event) => paths.extend(self.relevant(event)),
+        Err(RecvTimeoutError::Timeout) => break,
+        Err(RecvTimeoutError::Disconnected) => return None,
+      }
+    }
+    paths.sort();
+    paths.dedup();
+    Some(paths)
+  }
+}
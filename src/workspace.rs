@@ -0,0 +1,137 @@
+//! The cargo workspace the xtask is run in.
+//!
+//! [`root`] and [`metadata`] run cargo the first time they are called and
+//! cache the result for the rest of the process.
+
+use crate::{any_err::AnyErr, command_ext::CommandExt, result_ext::ResultExt};
+use serde::Deserialize;
+use std::{
+  collections::BTreeMap,
+  env,
+  path::{Path, PathBuf},
+  process::Command,
+  sync::OnceLock,
+};
+
+pub mod prelude {
+  pub use crate::workspace;
+}
+
+/// The directory containing the workspace's `Cargo.toml`
+pub fn root() -> Result<&'static Path, AnyErr> {
+  static ROOT: OnceLock<PathBuf> = OnceLock::new();
+  if let Some(root) = ROOT.get() {
+    return Ok(root);
+  }
+
+  let manifest = cargo()
+    .args(["locate-project", "--workspace", "--message-format", "plain"])
+    .read()
+    .context("failed to locate the workspace")?;
+  let root = match Path::new(manifest.trim_end()).parent() {
+    Some(root) => root.to_owned(),
+    None => {
+      return Err(
+        AnyErr::new("cargo returned an invalid manifest path")
+          .field("path", manifest.trim_end()),
+      )
+    }
+  };
+  Ok(ROOT.get_or_init(|| root))
+}
+
+/// The output of `cargo metadata` for the workspace, without its dependencies
+pub fn metadata() -> Result<&'static Metadata, AnyErr> {
+  static METADATA: OnceLock<Metadata> = OnceLock::new();
+  if let Some(metadata) = METADATA.get() {
+    return Ok(metadata);
+  }
+
+  let json = cargo()
+    .args(["metadata", "--format-version", "1", "--no-deps"])
+    .read()
+    .context("failed to get the workspace metadata")?;
+  let metadata = serde_json::from_str(&json)
+    .context("failed to parse the output of cargo metadata")?;
+  Ok(METADATA.get_or_init(|| metadata))
+}
+
+/// The cargo that is running the xtask, or the one on the path
+pub(crate) fn cargo() -> Command {
+  env::var_os("CARGO").map_or_else(|| Command::new("cargo"), Command::new)
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Metadata {
+  pub packages: Vec<Package>,
+  pub workspace_members: Vec<String>,
+  pub workspace_root: PathBuf,
+  pub target_directory: PathBuf,
+}
+
+impl Metadata {
+  /// The packages that are members of the workspace
+  pub fn members(&self) -> impl Iterator<Item = &Package> {
+    self
+      .packages
+      .iter()
+      .filter(|package| self.workspace_members.contains(&package.id))
+  }
+
+  pub fn package(&self, name: &str) -> Option<&Package> {
+    self.packages.iter().find(|package| package.name == name)
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Package {
+  pub name: String,
+  pub version: String,
+  pub id: String,
+  pub manifest_path: PathBuf,
+  /// Each feature and the features and dependencies it enables
+  pub features: BTreeMap<String, Vec<String>>,
+  pub targets: Vec<Target>,
+  pub dependencies: Vec<Dependency>,
+}
+
+impl Package {
+  /// The directory containing the package's `Cargo.toml`
+  pub fn dir(&self) -> &Path {
+    self.manifest_path.parent().unwrap_or(Path::new(""))
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Target {
+  pub name: String,
+  /// e.g. `lib`, `bin`, `test`, `example` or `custom-build`
+  pub kind: Vec<String>,
+  pub src_path: PathBuf,
+  #[serde(default, rename = "required-features")]
+  pub required_features: Vec<String>,
+}
+
+impl Target {
+  pub fn is_lib(&self) -> bool {
+    self.kind.iter().any(|kind| kind.ends_with("lib"))
+  }
+
+  pub fn is_bin(&self) -> bool {
+    self.kind.iter().any(|kind| kind == "bin")
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Dependency {
+  pub name: String,
+  /// The version requirement
+  pub req: String,
+  /// `dev`, `build`, or `None` for a normal dependency
+  pub kind: Option<String>,
+  pub optional: bool,
+}
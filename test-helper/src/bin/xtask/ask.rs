@@ -0,0 +1,11 @@
+use xtask_utils::{prelude::*, prompt::Confirm};
+
+#[derive(clap::Parser)]
+/// Ask before printing
+pub struct Cli {}
+
+pub fn main(Cli {}: Cli) -> ! {
+  Confirm::new("Delete everything?").require().exit_on_err();
+  println!("confirmed");
+  std::process::exit(0);
+}
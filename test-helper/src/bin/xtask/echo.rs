@@ -0,0 +1,10 @@
+#[derive(clap::Parser)]
+/// Print the given words
+pub struct Cli {
+  words: Vec<String>,
+}
+
+pub fn main(Cli { words }: Cli) -> ! {
+  println!("{}", words.join(" "));
+  std::process::exit(0);
+}
@@ -0,0 +1,7 @@
+#[derive(clap::Parser)]
+/// Exit with a non-zero code
+pub struct Cli {}
+
+pub fn main(Cli {}: Cli) -> ! {
+  std::process::exit(3);
+}
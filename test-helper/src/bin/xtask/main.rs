@@ -0,0 +1,5 @@
+xtask_utils::tasks!(echo fail pass script step ask nested);
+
+fn main() -> ! {
+  Task::run_all();
+}
@@ -0,0 +1,14 @@
+use std::{env, process::Command};
+use xtask_utils::prelude::*;
+
+#[derive(clap::Parser)]
+/// Chain tasks in a nested run of the xtask
+pub struct Cli {}
+
+pub fn main(Cli {}: Cli) -> ! {
+  let exe = env::current_exe().unwrap();
+  Command::new(exe)
+    .args(["echo", "a", "--", "echo", "b"])
+    .exec()
+    .exit()
+}
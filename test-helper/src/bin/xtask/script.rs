@@ -0,0 +1,14 @@
+use xtask_utils::{prelude::*, script};
+
+#[derive(clap::Parser)]
+/// Run a couple of commands
+pub struct Cli {}
+
+pub fn main(Cli {}: Cli) -> ! {
+  script! {
+    ("echo one")
+    ("echo two" | "cat")
+  }
+  .exit_on_err();
+  std::process::exit(0);
+}
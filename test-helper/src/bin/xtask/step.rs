@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+use xtask_utils::{any_err::AnyErr, prelude::*, step::Step};
+
+#[derive(clap::Parser)]
+/// Print whether a step with the given input ran
+pub struct Cli {
+  name: String,
+  input: PathBuf,
+}
+
+pub fn main(Cli { name, input }: Cli) -> ! {
+  let ran = Step::new(name)
+    .input(input)
+    .run(|| Ok::<_, AnyErr>(()))
+    .exit_on_err();
+  println!("{}", if ran { "ran" } else { "skipped" });
+  std::process::exit(0);
+}
@@ -1,13 +1,169 @@
 use std::{
   env,
-  process::{self, Command},
+  error::Error,
+  fs,
+  process::{self, Command, ExitCode},
+};
+use xtask_utils::{
+  any_err::AnyErr,
+  cargo::Cargo,
+  prelude::*,
+  result_ext::{panic::PanicHook, report},
 };
-use xtask_utils::{any_err::AnyErr, prelude::*};
 
 fn main() -> ! {
   let mut args = env::args();
   match args.nth(1).expect("test-helper run with no args").as_str() {
     "err-exit" => AnyErr::new("example error").exit(),
+    "backtrace-err" => {
+      env::set_var("RUST_BACKTRACE", "1");
+      let res: Result<(), AnyErr> = Err(AnyErr::new("error1"));
+      res.context("error 2").exit();
+    }
+    "lib-backtrace-err" => {
+      env::set_var("RUST_LIB_BACKTRACE", "1");
+      let res: Result<(), AnyErr> = Err(AnyErr::new("error1"));
+      res.context("error 2").exit();
+    }
+    "detailed-err" => {
+      let res: Result<(), AnyErr> = Err(
+        AnyErr::new("missing tool")
+          .field("tool", "protoc")
+          .hint("run `cargo xtask setup`"),
+      );
+      res
+        .context("failed to generate code")
+        .map_err(|err| err.field("path", "proto/api.proto").note("a note"))
+        .exit();
+    }
+    "aggregate-err" => {
+      let res: Result<Vec<u8>, _> = ["1", "x", "300"]
+        .into_iter()
+        .map(|s| s.parse::<u8>().context(format!("invalid number `{s}`")))
+        .collect_errors();
+      res.map(drop).context("failed to parse the numbers").exit();
+    }
+    "boxed-err-exit" => {
+      let err: Box<dyn Error> = AnyErr::new("error1").with_exit_code(3).into();
+      err.exit();
+    }
+    "exit-code-err" => {
+      let res: Result<(), AnyErr> =
+        Err(AnyErr::new("error1").with_exit_code(4));
+      res.context("error 2").exit();
+    }
+    "missing-command-err" => {
+      Command::new("xtask-utils-missing-command").wait().exit();
+    }
+    "ok-exit-code" => {
+      let res: Result<ExitCode, AnyErr> = Ok(ExitCode::from(6));
+      res.exit_with_code();
+    }
+    "ok-exit-value" => {
+      let res: Result<i32, AnyErr> = Ok(6);
+      res.exit();
+    }
+    "ok-exit-status" => {
+      Command::new("sh")
+        .args(["-c", "exit 5"])
+        .status()
+        .exit_with_code();
+    }
+    "cargo-messages-err" => {
+      let dir = env::temp_dir().join("xtask-utils/cargo-messages-err");
+      fs::create_dir_all(dir.join("src")).unwrap();
+      fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"broken\"\nedition = \"2021\"\n",
+      )
+      .unwrap();
+      fs::write(dir.join("src/lib.rs"), "pub fn broken() -> u32 { \"\" }\n")
+        .unwrap();
+      Cargo::check()
+        .arg(format!(
+          "--manifest-path={}",
+          dir.join("Cargo.toml").display()
+        ))
+        .messages()
+        .map(drop)
+        .exit();
+    }
+    "print-json" => {
+      println!(r#"{{"reason":1,"not":"a cargo message"}}"#);
+      process::exit(0);
+    }
+    #[cfg(unix)]
+    "install-tools" => {
+      use std::os::unix::fs::PermissionsExt;
+      use xtask_utils::tools::{self, require_tool};
+
+      // a cargo that installs a script printing the requested version
+      let dir = env::temp_dir()
+        .join(format!("xtask-utils/install-tools-{}", process::id()));
+      fs::create_dir_all(&dir).unwrap();
+      let log = dir.join("log");
+      let cargo = dir.join("cargo");
+      fs::write(
+        &cargo,
+        format!(
+          r#"#!/bin/sh
+[ "$1" = install ] || exec "{real}" "$@"
+echo "$@" >> "{log}"
+[ "$6" = xtask-utils-broken ] && exit 101
+mkdir -p "$3/bin"
+printf '#!/bin/sh\necho fake %s\n' "$5" > "$3/bin/$6"
+chmod +x "$3/bin/$6"
+"#,
+          real = env::var("CARGO").unwrap(),
+          log = log.display()
+        ),
+      )
+      .unwrap();
+      fs::set_permissions(&cargo, fs::Permissions::from_mode(0o755)).unwrap();
+      env::set_var("CARGO", &cargo);
+
+      let root = tools::install_root().unwrap();
+      fs::remove_file(root.join("bin/xtask-utils-fake-tool")).ok();
+      let tool = require_tool("xtask-utils-fake-tool")
+        .cargo_install("xtask-utils-fake-tool", "1.0.0");
+      println!("{}", tool.find().unwrap().display());
+      println!(
+        "{}",
+        tools::which("xtask-utils-fake-tool").unwrap().display()
+      );
+      tool.find().unwrap();
+      require_tool("xtask-utils-fake-tool")
+        .cargo_install("xtask-utils-fake-tool", "1.1.0")
+        .find()
+        .unwrap();
+
+      let err = require_tool("xtask-utils-broken")
+        .cargo_install("xtask-utils-broken", "1.0.0")
+        .hint("check your registry")
+        .find()
+        .unwrap_err();
+      println!("{err}: {}", err.hints().collect::<Vec<_>>().join(", "));
+      print!("{}", fs::read_to_string(&log).unwrap());
+      fs::remove_dir_all(&dir).unwrap();
+      process::exit(0);
+    }
+    "panic-hook-backtrace" => {
+      env::set_var("RUST_BACKTRACE", "1");
+      PanicHook::new().install();
+      panic!("oh no");
+    }
+    "panic-hook" => {
+      PanicHook::new()
+        .bug_message("this is a bug in the xtask")
+        .exit_code(70)
+        .install();
+      panic!("oh no");
+    }
+    "compact-err" => {
+      report::set_reporter(report::Compact);
+      let res: Result<(), AnyErr> = Err(AnyErr::new("error1"));
+      res.context("error 2").exit();
+    }
     "res-exit" => {
       let res: Result<(), AnyErr> = match args
         .next()
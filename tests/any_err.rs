@@ -1,4 +1,5 @@
-use xtask_utils::any_err::AnyErr;
+use std::{error::Error, thread};
+use xtask_utils::any_err::{self, AnyErr, BoxError, Errors};
 
 #[test]
 fn retains_message() {
@@ -7,3 +8,212 @@ fn retains_message() {
   let err = AnyErr::with_source("hi again", err);
   assert!(err.to_string().contains("hi again"));
 }
+
+#[test]
+fn details() {
+  let err = AnyErr::new("failed")
+    .field("path", "src/main.rs")
+    .field("exit_code", 2)
+    .note("a note")
+    .hint("a hint");
+  assert_eq!(
+    err.fields().collect::<Vec<_>>(),
+    [("path", "src/main.rs"), ("exit_code", "2")]
+  );
+  assert_eq!(err.get_field("exit_code"), Some("2"));
+  assert_eq!(err.get_field("command"), None);
+  assert_eq!(err.notes().collect::<Vec<_>>(), ["a note"]);
+  assert_eq!(err.hints().collect::<Vec<_>>(), ["a hint"]);
+  assert_eq!(err.to_string(), "failed");
+}
+
+#[test]
+fn location() {
+  let err = AnyErr::new("failed");
+  assert_eq!(err.location().file(), file!());
+  assert_eq!(err.location().line(), line!() - 2);
+}
+
+#[test]
+fn send_sync() {
+  fn assert_send_sync<T: Send + Sync + 'static>() {}
+  assert_send_sync::<AnyErr>();
+}
+
+#[test]
+fn crosses_threads() {
+  let handle = thread::spawn(|| -> Result<(), AnyErr> {
+    Err(AnyErr::with_source(
+      "worker failed",
+      AnyErr::new("root cause"),
+    ))
+  });
+  let err = any_err::join(handle).unwrap_err();
+  assert_eq!(err.to_string(), "worker failed");
+  assert_eq!(err.source().unwrap().to_string(), "root cause");
+
+  let handle = thread::spawn(|| -> Result<(), AnyErr> { panic!("oh no") });
+  let err = any_err::join(handle).unwrap_err();
+  assert_eq!(err.to_string(), "thread panicked: oh no");
+}
+
+#[test]
+fn from_boxed() {
+  let boxed: BoxError =
+    AnyErr::with_source("outer", AnyErr::new("inner")).into();
+  let err = AnyErr::from(boxed);
+  assert_eq!(err.to_string(), "outer");
+  assert_eq!(err.source().unwrap().to_string(), "inner");
+  assert!(err.source().unwrap().source().is_none());
+}
+
+#[test]
+fn macros() {
+  fn check(n: i32) -> Result<i32, AnyErr> {
+    xtask_utils::ensure!(n >= 0);
+    xtask_utils::ensure!(n != 1, "{n} is not allowed");
+    if n > 10 {
+      xtask_utils::bail!("{n} is too big");
+    }
+    Ok(n)
+  }
+
+  assert_eq!(check(2).unwrap(), 2);
+  assert_eq!(
+    check(-1).unwrap_err().to_string(),
+    "condition failed: `n >= 0`"
+  );
+  assert_eq!(check(1).unwrap_err().to_string(), "1 is not allowed");
+  let err = check(11).unwrap_err();
+  assert_eq!(err.to_string(), "11 is too big");
+  assert_eq!(err.location().file(), file!());
+
+  let err = xtask_utils::any_err!("{} and {}", 1, 2);
+  assert_eq!(err.to_string(), "1 and 2");
+}
+
+#[test]
+fn from_std_errors() {
+  fn parse(s: &str) -> Result<u8, AnyErr> {
+    Ok(s.parse()?)
+  }
+  fn read() -> Result<String, AnyErr> {
+    Ok(std::fs::read_to_string("does/not/exist")?)
+  }
+
+  let err = parse("300").unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    "300".parse::<u8>().unwrap_err().to_string()
+  );
+  assert_eq!(err.location().file(), file!());
+  let err = read().unwrap_err();
+  assert!(err.source().is_none());
+  assert_eq!(
+    err.to_string(),
+    std::fs::read_to_string("does/not/exist")
+      .unwrap_err()
+      .to_string()
+  );
+}
+
+#[test]
+fn aggregate() {
+  let mut errors = Errors::new();
+  assert!(errors.is_empty());
+  errors.push(AnyErr::new("first"));
+  errors.push("x".parse::<u8>().unwrap_err());
+  assert_eq!(errors.len(), 2);
+  assert_eq!(errors.to_string(), "2 errors occurred");
+  assert_eq!(
+    errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    ["first", "invalid digit found in string"]
+  );
+
+  let errors: Errors = [AnyErr::new("only")].into_iter().collect();
+  assert_eq!(errors.to_string(), "1 error occurred");
+  assert!(errors.into_result(()).is_err());
+  assert_eq!(
+    Errors::with_message("checks failed")
+      .into_result(3)
+      .unwrap(),
+    3
+  );
+}
+
+#[test]
+fn downcast() {
+  let io_err = std::io::Error::from(std::io::ErrorKind::NotFound);
+  let err = AnyErr::with_source(
+    "outer",
+    AnyErr::from(io_err).field("path", "xtask.toml"),
+  );
+  assert_eq!(
+    err.chain().map(ToString::to_string).collect::<Vec<_>>(),
+    ["outer", "entity not found"]
+  );
+  assert_eq!(err.root_cause().to_string(), "entity not found");
+  assert_eq!(
+    err.downcast_ref::<std::io::Error>().unwrap().kind(),
+    std::io::ErrorKind::NotFound
+  );
+  assert_eq!(err.downcast_ref::<AnyErr>().unwrap().to_string(), "outer");
+  assert!(err.downcast_ref::<std::fmt::Error>().is_none());
+
+  let err = AnyErr::from(std::fmt::Error);
+  assert!(err.downcast_ref::<std::fmt::Error>().is_some());
+  assert_eq!(err.chain().count(), 1);
+}
+
+#[cfg(feature = "command_ext")]
+#[test]
+fn downcast_command_error() {
+  use std::process::Command;
+  use xtask_utils::command_ext::{CommandExt, ErrorKind};
+
+  let err = AnyErr::from(
+    Command::new("xtask-utils-missing-command")
+      .wait()
+      .unwrap_err(),
+  );
+  let source = match err
+    .downcast_ref()
+    .map(xtask_utils::command_ext::Error::kind)
+  {
+    Some(ErrorKind::Spawn(source)) => source,
+    kind => panic!("unexpected error kind {kind:?}"),
+  };
+  assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn exit_codes() {
+  use xtask_utils::exit::{self, ProvideExitCode};
+
+  #[derive(Debug)]
+  struct Custom;
+  impl std::fmt::Display for Custom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      f.write_str("custom")
+    }
+  }
+  impl Error for Custom {}
+  impl ProvideExitCode for Custom {
+    fn exit_code(&self) -> Option<i32> {
+      Some(9)
+    }
+  }
+
+  let err = AnyErr::with_source("outer", AnyErr::new("inner"));
+  assert_eq!(exit::code_of(&err), exit::DEFAULT_CODE);
+  let err =
+    AnyErr::with_source("outer", AnyErr::new("inner").with_exit_code(3));
+  assert_eq!(exit::code_of(&err), 3);
+  let err = AnyErr::new("outer").with_exit_code(2);
+  assert_eq!(exit::code_of(&err), 2);
+
+  let err = AnyErr::with_source("outer", Custom);
+  assert_eq!(exit::code_of(&err), exit::DEFAULT_CODE);
+  exit::register::<Custom>();
+  assert_eq!(exit::code_of(&err), 9);
+}
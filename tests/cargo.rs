@@ -0,0 +1,212 @@
+use self::common::*;
+use std::ffi::OsStr;
+use xtask_utils::cargo::{message, Cargo, Message};
+
+mod common;
+
+fn args(cargo: &Cargo) -> Vec<&str> {
+  // leak the command so its arguments can be borrowed for the assertions
+  let command = Box::leak(Box::new(cargo.command()));
+  command
+    .get_args()
+    .map(|arg| arg.to_str().unwrap())
+    .collect()
+}
+
+#[test]
+fn builds_command() {
+  let cargo = Cargo::clippy()
+    .package("xtask-utils")
+    .package("test-helper")
+    .feature("any_err")
+    .features(["script", "cargo"])
+    .no_default_features()
+    .target("x86_64-unknown-linux-gnu")
+    .release()
+    .json()
+    .arg("--all-targets")
+    .trailing_args(["-D", "warnings"]);
+  assert_eq!(
+    args(&cargo),
+    [
+      "clippy",
+      "-p",
+      "xtask-utils",
+      "-p",
+      "test-helper",
+      "--features",
+      "any_err,script,cargo",
+      "--no-default-features",
+      "--target",
+      "x86_64-unknown-linux-gnu",
+      "--profile",
+      "release",
+      "--message-format=json",
+      "--all-targets",
+      "--",
+      "-D",
+      "warnings",
+    ]
+  );
+  assert_eq!(
+    args(&Cargo::test().workspace().all_features()),
+    ["test", "--workspace", "--all-features"]
+  );
+  assert_eq!(
+    args(&Cargo::build().json_ansi()),
+    ["build", "--message-format=json-diagnostic-rendered-ansi"]
+  );
+}
+
+#[test]
+fn uses_cargo_env() {
+  // cargo sets `CARGO` for the tests it runs
+  let command = Cargo::build().command();
+  assert_eq!(
+    command.get_program(),
+    OsStr::new(env!("CARGO")),
+    "the program should be the cargo running the tests"
+  );
+}
+
+#[test]
+fn parses_messages() {
+  let output = concat!(
+    r#"{"reason":"compiler-artifact","package_id":"path+file:///a#0.1.0","#,
+    r#""target":{"name":"a","kind":["bin"],"src_path":"/a/src/main.rs"},"#,
+    r#""filenames":["/a/target/debug/a"],"executable":"/a/target/debug/a","#,
+    r#""fresh":false}"#,
+    "\n",
+    "not json, e.g. printed by `cargo run`\n",
+    "{\"json\": \"printed by `cargo run`\"}\n",
+    r#"{"reason":"compiler-message","package_id":"path+file:///a#0.1.0","#,
+    r#""target":{"name":"a","kind":["bin"],"src_path":"/a/src/main.rs"},"#,
+    r#""message":{"message":"unused variable: `x`","level":"warning","#,
+    r#""code":{"code":"unused_variables","explanation":null},"#,
+    r#""rendered":"warning: unused variable: `x`\n","spans":[]}}"#,
+    "\n",
+    r#"{"reason":"build-script-executed","package_id":"path+file:///a#0.1.0","#,
+    r#""linked_libs":["z"],"linked_paths":[],"cfgs":["has_z"],"#,
+    r#""env":[["Z_VERSION","1.3"]],"out_dir":"/a/target/debug/build/out"}"#,
+    "\n",
+    r#"{"reason":"build-finished","success":true}"#,
+    "\n",
+    r#"{"reason":"something-new"}"#,
+    "\n",
+  );
+  let messages: Vec<_> = message::parse(output).map(Result::unwrap).collect();
+  assert_eq!(messages.len(), 5);
+
+  let Message::CompilerArtifact(artifact) = &messages[0] else {
+    panic!("expected an artifact, got {:?}", messages[0]);
+  };
+  assert!(artifact.target.is_bin());
+  assert_eq!(
+    artifact.executable.as_deref(),
+    Some("/a/target/debug/a".as_ref())
+  );
+
+  let Message::CompilerMessage(message) = &messages[1] else {
+    panic!("expected a diagnostic, got {:?}", messages[1]);
+  };
+  assert_eq!(message.message.level, "warning");
+  assert_eq!(
+    message.message.code.as_ref().unwrap().code,
+    "unused_variables"
+  );
+
+  let Message::BuildScriptExecuted(script) = &messages[2] else {
+    panic!("expected a build script, got {:?}", messages[2]);
+  };
+  assert_eq!(script.cfgs, ["has_z"]);
+  assert_eq!(script.env, [("Z_VERSION".to_owned(), "1.3".to_owned())]);
+
+  assert!(
+    matches!(&messages[3], Message::BuildFinished(finished) if finished.success)
+  );
+  assert!(matches!(messages[4], Message::Unknown));
+
+  let streamed = message::read(output.as_bytes()).count();
+  assert_eq!(streamed, messages.len());
+}
+
+#[test]
+fn runs_cargo() {
+  let messages = Cargo::build()
+    .arg("--manifest-path=test-helper/Cargo.toml")
+    .messages()
+    .unwrap();
+  assert!(messages.iter().any(|message| matches!(
+    message,
+    Message::CompilerArtifact(artifact)
+      if artifact.target.name == "test-helper"
+        && artifact.executable.is_some()
+  )));
+  assert!(matches!(
+    messages.last(),
+    Some(Message::BuildFinished(finished)) if finished.success
+  ));
+}
+
+#[test]
+fn returns_artifacts() {
+  let artifacts = Cargo::build()
+    .arg("--manifest-path=test-helper/Cargo.toml")
+    .artifacts()
+    .unwrap();
+  let helper = artifacts
+    .iter()
+    .find(|artifact| artifact.target.name == "test-helper")
+    .unwrap();
+  assert!(helper.executable.as_ref().unwrap().exists());
+  assert!(artifacts
+    .iter()
+    .any(|artifact| artifact.target.name == "xtask_utils"));
+}
+
+#[test]
+fn artifacts_passes_through_json_output() {
+  let artifacts = Cargo::run()
+    .args([
+      "--manifest-path=test-helper/Cargo.toml",
+      "--bin=test-helper",
+    ])
+    .trailing_args(["print-json"])
+    .artifacts()
+    .unwrap();
+  assert!(artifacts
+    .iter()
+    .any(|artifact| artifact.target.name == "test-helper"));
+}
+
+#[test]
+fn messages_skips_json_output() {
+  let messages = Cargo::run()
+    .args([
+      "--manifest-path=test-helper/Cargo.toml",
+      "--bin=test-helper",
+    ])
+    .trailing_args(["print-json"])
+    .messages()
+    .unwrap();
+  assert!(matches!(
+    messages.last(),
+    Some(Message::BuildFinished(finished)) if finished.success
+  ));
+}
+
+#[test]
+fn artifacts_fails_with_cargo() {
+  let err = Cargo::build()
+    .arg("--manifest-path=does-not-exist/Cargo.toml")
+    .artifacts()
+    .unwrap_err();
+  assert!(err.to_string().contains("cargo build"));
+}
+
+#[test]
+fn messages_prints_diagnostics_on_failure() {
+  let stderr = run_helper_stderr(false, ["cargo-messages-err"]);
+  assert!(stderr.contains("mismatched types"), "{stderr}");
+  assert!(stderr.contains("cargo check"), "{stderr}");
+}
@@ -1,18 +1,106 @@
+#![allow(dead_code)]
+
 use std::{
-  env,
+  env, fs,
   io::{self, Write},
-  process::Command,
+  ops::Deref,
+  path::{Path, PathBuf},
+  process::{self, Command, Output, Stdio},
+  sync::atomic::{AtomicUsize, Ordering},
 };
 
+/// A directory for a single test, removed with everything in it when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+  /// Create an empty directory starting with `name` that no other test uses
+  pub fn new(name: &str) -> Self {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let dir = env::temp_dir().join(env!("CARGO_PKG_NAME")).join(format!(
+      "{name}-{}-{}",
+      process::id(),
+      COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    // left behind by an earlier run that had the same pid
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    Self(dir)
+  }
+}
+
+impl Deref for TempDir {
+  type Target = Path;
+
+  fn deref(&self) -> &Path {
+    &self.0
+  }
+}
+
+impl AsRef<Path> for TempDir {
+  fn as_ref(&self) -> &Path {
+    &self.0
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    fs::remove_dir_all(&self.0).ok();
+  }
+}
+
 pub fn run_helper(
   exit_success: bool,
   args: impl IntoIterator<Item = &'static str>,
 ) -> String {
+  String::from_utf8(run_bin("test-helper", exit_success, args).stdout).unwrap()
+}
+
+/// Like [`run_helper`] but returns what the helper wrote to stderr
+pub fn run_helper_stderr(
+  exit_success: bool,
+  args: impl IntoIterator<Item = &'static str>,
+) -> String {
+  String::from_utf8(run_bin("test-helper", exit_success, args).stderr).unwrap()
+}
+
+/// Run the helper, expecting it to fail, and return its exit code
+pub fn run_helper_code(
+  args: impl IntoIterator<Item = &'static str>,
+) -> Option<i32> {
+  run_bin("test-helper", false, args).status.code()
+}
+
+pub fn run_xtask(
+  exit_success: bool,
+  args: impl IntoIterator<Item = &'static str>,
+) -> String {
+  String::from_utf8(run_bin("xtask", exit_success, args).stdout).unwrap()
+}
+
+fn run_bin(
+  bin: &str,
+  exit_success: bool,
+  args: impl IntoIterator<Item = &'static str>,
+) -> Output {
   let mut cargo =
     env::var_os("CARGO").map_or_else(|| Command::new("cargo"), Command::new);
   let output = cargo
-    .args(["run", "--manifest-path=test-helper/Cargo.toml", "--"])
+    .args([
+      "run",
+      "--manifest-path=test-helper/Cargo.toml",
+      "--bin",
+      bin,
+    ])
+    .arg("--")
     .args(args)
+    // keep the error output the same regardless of the environment
+    .env_remove("RUST_BACKTRACE")
+    .env_remove("RUST_LIB_BACKTRACE")
+    .env_remove("CLICOLOR_FORCE")
+    // never wait for an answer to a prompt
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
     .spawn()
     .expect("failed spawning test-helper")
     .wait_with_output()
@@ -31,5 +119,5 @@ pub fn run_helper(
     }
   }
 
-  String::from_utf8(output.stdout).unwrap()
+  output
 }
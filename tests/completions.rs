@@ -0,0 +1,24 @@
+use self::common::*;
+
+mod common;
+
+#[test]
+fn bash() {
+  let script = run_xtask(true, ["completions", "bash"]);
+  assert!(script.contains("complete -F _xtask"));
+  assert!(script.contains("echo"));
+  assert!(script.contains("--timings"));
+}
+
+#[test]
+fn custom_bin_name() {
+  let script = run_xtask(true, ["completions", "fish", "--bin-name=my-xtask"]);
+  assert!(script.contains("complete -c my-xtask"));
+}
+
+#[test]
+fn man() {
+  let page = run_xtask(true, ["man"]);
+  assert!(page.starts_with(".ie"));
+  assert!(page.contains(".TH xtask"));
+}
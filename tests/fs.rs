@@ -0,0 +1,112 @@
+use self::common::*;
+use xtask_utils::fs;
+
+mod common;
+
+#[test]
+fn errors_name_the_path() {
+  let dir = TempDir::new("fs-errors");
+  let missing = dir.join("missing.txt");
+  let err = fs::read_to_string(&missing).unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    format!("failed to read {}", missing.display())
+  );
+  assert_eq!(err.location().file(), file!());
+
+  let err = fs::rename(&missing, dir.join("other.txt")).unwrap_err();
+  assert!(err.to_string().starts_with("failed to rename"));
+}
+
+#[test]
+fn read_write() {
+  let dir = TempDir::new("fs-read-write");
+  let path = dir.join("file.txt");
+  fs::write(&path, "hello").unwrap();
+  assert_eq!(fs::read(&path).unwrap(), b"hello");
+  fs::copy(&path, dir.join("copy.txt")).unwrap();
+  fs::remove_file(&path).unwrap();
+  assert_eq!(fs::read_to_string(dir.join("copy.txt")).unwrap(), "hello");
+  fs::remove_dir_all(&dir).unwrap();
+  assert!(!dir.exists());
+}
+
+#[test]
+fn copy_dir() {
+  let dir = TempDir::new("fs-copy-dir");
+  let from = dir.join("from");
+  fs::create_dir_all(from.join("src/nested")).unwrap();
+  fs::create_dir_all(from.join("target")).unwrap();
+  fs::write(from.join("Cargo.toml"), "").unwrap();
+  fs::write(from.join("src/lib.rs"), "").unwrap();
+  fs::write(from.join("src/nested/mod.rs"), "").unwrap();
+  fs::write(from.join("target/out"), "").unwrap();
+
+  let to = dir.join("to");
+  let copied =
+    fs::copy_dir_with(&from, &to, |path| !path.starts_with("target")).unwrap();
+  assert_eq!(copied, 3);
+  assert!(to.join("src/nested/mod.rs").exists());
+  assert!(!to.join("target").exists());
+
+  assert_eq!(fs::copy_dir(&from, dir.join("all")).unwrap(), 4);
+}
+
+#[cfg(unix)]
+#[test]
+fn copy_dir_symlinks() {
+  use std::{os::unix::fs::symlink, path::Path};
+
+  let dir = TempDir::new("fs-copy-dir-symlinks");
+  let from = dir.join("from");
+  fs::create_dir_all(from.join("src")).unwrap();
+  fs::write(from.join("src/lib.rs"), "").unwrap();
+  // following these would copy `from` into itself forever
+  symlink("..", from.join("src/parent")).unwrap();
+  symlink("src", from.join("link")).unwrap();
+  symlink("lib.rs", from.join("src/main.rs")).unwrap();
+
+  let to = dir.join("to");
+  assert_eq!(fs::copy_dir(&from, &to).unwrap(), 4);
+  assert_eq!(
+    std::fs::read_link(to.join("src/parent")).unwrap(),
+    Path::new("..")
+  );
+  assert_eq!(
+    std::fs::read_link(to.join("link")).unwrap(),
+    Path::new("src")
+  );
+  assert!(to.join("src/main.rs").is_file());
+
+  // copying again replaces the links
+  assert_eq!(fs::copy_dir(&from, &to).unwrap(), 4);
+}
+
+#[test]
+fn write_atomic() {
+  let dir = TempDir::new("fs-atomic");
+  let path = dir.join("file.txt");
+  fs::write_atomic(&path, "one").unwrap();
+  fs::write_atomic(&path, "two").unwrap();
+  assert_eq!(fs::read_to_string(&path).unwrap(), "two");
+  assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+  let err = fs::write_atomic(dir.join("missing/file.txt"), "").unwrap_err();
+  assert!(err.to_string().starts_with("failed to write"));
+  assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn write_if_changed() {
+  let dir = TempDir::new("fs-if-changed");
+  let path = dir.join("file.txt");
+  assert!(fs::write_if_changed(&path, "one").unwrap());
+  let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+  assert!(!fs::write_if_changed(&path, "one").unwrap());
+  assert_eq!(
+    std::fs::metadata(&path).unwrap().modified().unwrap(),
+    modified
+  );
+  assert!(fs::write_if_changed(&path, "two").unwrap());
+  assert_eq!(fs::read_to_string(&path).unwrap(), "two");
+}
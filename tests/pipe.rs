@@ -1,19 +1,13 @@
-use std::{
-  env, fs,
-  time::{SystemTime, UNIX_EPOCH},
-};
+use self::common::*;
+use std::{fs, process::Command};
 use xtask_utils::cmd;
 
+mod common;
+
 #[test]
 fn happy_path() {
-  let mut temp = env::temp_dir().join(env!("CARGO_PKG_NAME")).join(
-    SystemTime::now()
-      .duration_since(UNIX_EPOCH)
-      .unwrap()
-      .as_micros()
-      .to_string(),
-  );
-  temp.set_extension("txt");
+  let dir = TempDir::new("pipe");
+  let temp = dir.join("out.txt");
 
   cmd!("echo hello world" | "cat" | "cat" > temp.clone())
     .wait()
@@ -30,6 +24,25 @@ fn happy_path() {
   )
 }
 
+#[test]
+fn command_section() {
+  let dir = TempDir::new("pipe-command");
+  let temp = dir.join("out.txt");
+
+  let mut echo = Command::new("sh");
+  echo
+    .args(["-c", "echo $GREETING"])
+    .env("GREETING", "hi there");
+  let pipe = cmd!(echo | "cat" > temp.clone());
+  assert_eq!(
+    pipe.to_string(),
+    format!("sh -c echo $GREETING | cat > {}", temp.display())
+  );
+  pipe.wait().unwrap();
+
+  assert_eq!(fs::read_to_string(&temp).unwrap().trim(), "hi there");
+}
+
 #[test]
 fn single_command_fail() {
   let result = cmd!(FAIL_CMD).spawn().unwrap().join().unwrap();
@@ -0,0 +1,103 @@
+use self::common::*;
+use xtask_utils::{
+  exit,
+  prompt::{Cancelled, Confirm, Input, Select},
+};
+
+mod common;
+
+#[test]
+fn confirm() {
+  let confirm = Confirm::new("Delete?");
+  let mut output = Vec::new();
+  assert!(confirm.ask_with("y\n".as_bytes(), &mut output).unwrap());
+  assert_eq!(output, b"Delete? [y/N] ");
+  assert!(!confirm.ask_with("No\n".as_bytes(), Vec::new()).unwrap());
+  assert!(!confirm.ask_with("\n".as_bytes(), Vec::new()).unwrap());
+
+  let mut output = Vec::new();
+  assert!(confirm
+    .ask_with("maybe\nyes\n".as_bytes(), &mut output)
+    .unwrap());
+  assert_eq!(
+    String::from_utf8(output).unwrap(),
+    "Delete? [y/N] please answer y or n\nDelete? [y/N] "
+  );
+
+  let confirm = Confirm::new("Delete?").default(true);
+  let mut output = Vec::new();
+  assert!(confirm.ask_with("\n".as_bytes(), &mut output).unwrap());
+  assert_eq!(output, b"Delete? [Y/n] ");
+}
+
+#[test]
+fn select() {
+  let select = Select::new("Registry", ["crates.io", "internal"]).default(1);
+  let mut output = Vec::new();
+  assert_eq!(select.ask_with("1\n".as_bytes(), &mut output).unwrap(), 0);
+  assert_eq!(
+    String::from_utf8(output).unwrap(),
+    "Registry\n  1) crates.io\n  2) internal (default)\n[1-2] "
+  );
+  assert_eq!(select.ask_with("\n".as_bytes(), Vec::new()).unwrap(), 1);
+
+  let mut output = Vec::new();
+  assert_eq!(
+    select
+      .ask_with("3\nx\n2\n".as_bytes(), &mut output)
+      .unwrap(),
+    1
+  );
+  let output = String::from_utf8(output).unwrap();
+  assert_eq!(
+    output.matches("please enter a number from [1-2]").count(),
+    2
+  );
+}
+
+#[test]
+fn select_checks_options() {
+  let select = Select::new("Registry", ["crates.io", "internal"]).default(2);
+  let err = select.ask().unwrap_err();
+  assert!(err.to_string().contains("not one of its options"), "{err}");
+  assert!(select.ask_with("1\n".as_bytes(), Vec::new()).is_err());
+
+  let select = Select::new("Registry", Vec::<String>::new());
+  let err = select.ask_with("1\n".as_bytes(), Vec::new()).unwrap_err();
+  assert!(err.to_string().contains("has no options"), "{err}");
+}
+
+#[test]
+fn input() {
+  let input = Input::new("Version");
+  let mut output = Vec::new();
+  assert_eq!(
+    input.ask_with(" 1.2.0 \n".as_bytes(), &mut output).unwrap(),
+    "1.2.0"
+  );
+  assert_eq!(output, b"Version ");
+
+  let input = Input::new("Version").default("1.0.0");
+  let mut output = Vec::new();
+  assert_eq!(
+    input.ask_with("\n".as_bytes(), &mut output).unwrap(),
+    "1.0.0"
+  );
+  assert_eq!(output, b"Version [1.0.0] ");
+}
+
+#[test]
+fn closing_stdin_cancels() {
+  let err = Confirm::new("Delete?")
+    .ask_with("".as_bytes(), Vec::new())
+    .unwrap_err();
+  assert_eq!(err.to_string(), "cancelled");
+  assert!(err.downcast_ref::<Cancelled>().is_some());
+  assert_eq!(exit::code_of(&err), 130);
+}
+
+#[test]
+fn without_terminal() {
+  assert_eq!(run_xtask(true, ["--yes", "ask"]), "confirmed\n");
+  assert_eq!(run_xtask(false, ["ask"]), "");
+}
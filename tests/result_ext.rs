@@ -41,10 +41,122 @@ mod error_ext {
   fn exit_err() {
     run_helper(false, ["res-exit", "err"]);
   }
+
+  #[test]
+  fn prints_backtrace() {
+    let stderr = run_helper_stderr(false, ["backtrace-err"]);
+    assert!(stderr.contains(
+      "error 2
+    at src/main.rs:"
+    ));
+    assert!(stderr.contains(
+      "Caused by:
+  error1
+      at src/main.rs:"
+    ));
+    assert!(stderr.contains("\nBacktrace:\n"));
+  }
+
+  #[test]
+  fn prints_lib_backtrace() {
+    let stderr = run_helper_stderr(false, ["lib-backtrace-err"]);
+    assert!(stderr.contains(
+      "error 2
+    at src/main.rs:"
+    ));
+    assert!(stderr.contains("\nBacktrace:\n"));
+  }
+
+  #[test]
+  fn prints_details() {
+    let stderr = run_helper_stderr(false, ["detailed-err"]);
+    assert!(stderr.ends_with(
+      "failed to generate code
+
+Caused by:
+  missing tool
+
+  path: proto/api.proto
+  tool: protoc
+
+note: a note
+hint: run `cargo xtask setup`
+"
+    ));
+  }
+
+  #[test]
+  fn exit_codes() {
+    assert_eq!(run_helper_code(["err-exit"]), Some(1));
+    assert_eq!(run_helper_code(["exit-code-err"]), Some(4));
+    assert_eq!(run_helper_code(["boxed-err-exit"]), Some(3));
+    assert_eq!(run_helper_code(["missing-command-err"]), Some(127));
+  }
+
+  #[test]
+  fn panic_hook() {
+    assert_eq!(run_helper_code(["panic-hook"]), Some(70));
+    let stderr = run_helper_stderr(false, ["panic-hook"]);
+    assert!(stderr.contains("thread 'main' panicked: oh no\n\n"));
+    assert!(stderr.contains("\n  location: src/main.rs:"));
+    assert!(stderr.ends_with("\nnote: this is a bug in the xtask\n"));
+  }
+
+  #[test]
+  fn panic_hook_location() {
+    // the error is created by the hook, so only the panic's location is shown
+    let stderr = run_helper_stderr(false, ["panic-hook-backtrace"]);
+    assert!(
+      stderr
+        .contains("thread 'main' panicked: oh no\n\n  location: src/main.rs:"),
+      "{stderr}"
+    );
+  }
+
+  #[test]
+  fn installed_reporter() {
+    let stderr = run_helper_stderr(false, ["compact-err"]);
+    assert!(stderr.ends_with("error 2: error1\n"));
+  }
+
+  #[test]
+  fn prints_aggregate() {
+    let stderr = run_helper_stderr(false, ["aggregate-err"]);
+    assert!(stderr.ends_with(
+      "failed to parse the numbers
+
+Caused by:
+  2 errors occurred
+
+1. invalid number `x`
+
+   Caused by:
+     invalid digit found in string
+
+2. invalid number `300`
+
+   Caused by:
+     number too large to fit in target type
+"
+    ));
+  }
 }
 
 mod result_ext {
   use super::*;
+  use xtask_utils::result_ext::ResultExt;
+
+  #[test]
+  fn context_location() {
+    let res: Result<(), _> = Err(AnyErr::new("first message"));
+    let err = res.context("second message").unwrap_err();
+    assert_eq!(err.location().file(), file!());
+    assert_eq!(err.location().line(), line!() - 2);
+
+    let res: Result<(), _> = Err(err);
+    let err = res.with_context(|| "third message").unwrap_err();
+    assert_eq!(err.location().line(), line!() - 1);
+  }
 
   #[test]
   fn exit_ok() {
@@ -55,4 +167,112 @@ mod result_ext {
   fn exit_err() {
     run_helper(false, ["res-exit-err", "err"]);
   }
+
+  #[test]
+  fn exit_ok_code() {
+    assert_eq!(run_helper_code(["ok-exit-code"]), Some(6));
+    assert_eq!(run_helper_code(["ok-exit-status"]), Some(5));
+    // other values don't carry a code
+    run_helper(true, ["ok-exit-value"]);
+  }
+}
+
+mod result_iter_ext {
+  use xtask_utils::result_ext::ResultIterExt;
+
+  #[test]
+  fn collect_errors() {
+    let values: Vec<u8> = ["1", "2"]
+      .iter()
+      .map(|s| s.parse::<u8>())
+      .collect_errors()
+      .unwrap();
+    assert_eq!(values, [1, 2]);
+
+    let errors = ["1", "x", "300"]
+      .iter()
+      .map(|s| s.parse::<u8>())
+      .collect_errors::<Vec<_>>()
+      .unwrap_err();
+    assert_eq!(errors.len(), 2);
+  }
+}
+
+mod report {
+  use xtask_utils::{
+    any_err::{AnyErr, Errors},
+    result_ext::report::{ColorChoice, Compact, Full, Json, Reporter},
+  };
+
+  fn render(reporter: impl Reporter, err: &AnyErr) -> String {
+    let mut out = Vec::new();
+    reporter.report(err, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+  }
+
+  fn example() -> AnyErr {
+    let errors: Errors = [
+      AnyErr::with_source("bad manifest", AnyErr::new("missing license")),
+      AnyErr::new("bad header").hint("run `cargo xtask fix`"),
+    ]
+    .into_iter()
+    .collect();
+    AnyErr::with_source("check failed", errors).field("crate", "xtask")
+  }
+
+  #[test]
+  fn compact() {
+    assert_eq!(
+      render(Compact, &example()),
+      "check failed: 2 errors occurred \
+       [bad manifest: missing license; bad header]\n"
+    );
+  }
+
+  #[test]
+  fn json() {
+    let err = AnyErr::new("failed").note("a \"note\"");
+    assert_eq!(
+      render(Json, &err),
+      format!(
+        concat!(
+          r#"{{"message":"failed","causes":[],"location":"{}","#,
+          r#""fields":{{}},"notes":["a \"note\""],"hints":[],"errors":[]}}"#,
+          "\n",
+        ),
+        err.location()
+      )
+    );
+    let json = render(Json, &example());
+    assert!(json.starts_with(
+      r#"{"message":"check failed","causes":["2 errors occurred"],"#
+    ));
+    assert!(json.contains(r#""fields":{"crate":"xtask"}"#));
+    assert!(json.contains(r#""hints":["run `cargo xtask fix`"]"#));
+  }
+
+  // the location and backtrace depend on whether a backtrace is captured, so
+  // only the parts that don't are checked
+  #[test]
+  fn colors() {
+    let err = AnyErr::new("failed").hint("try again");
+    let plain = render(
+      Full {
+        color: ColorChoice::Never,
+      },
+      &err,
+    );
+    assert!(plain.starts_with("failed\n"));
+    assert!(plain.contains("\nhint: try again\n"));
+    assert!(!plain.contains('\x1b'));
+
+    let colored = render(
+      Full {
+        color: ColorChoice::Always,
+      },
+      &err,
+    );
+    assert!(colored.starts_with("\x1b[1;31mfailed\x1b[0m\n"));
+    assert!(colored.contains("\n\x1b[1;32mhint:\x1b[0m try again\n"));
+  }
 }
@@ -0,0 +1,143 @@
+use self::common::*;
+use std::{fs, path::Path};
+use xtask_utils::scaffold::{Arg, NewTask, Returns};
+
+mod common;
+
+fn src_root(main: &str) -> TempDir {
+  let dir = TempDir::new("scaffold");
+  fs::write(dir.join("main.rs"), main).unwrap();
+  dir
+}
+
+fn main_rs(src_root: &Path) -> String {
+  fs::read_to_string(src_root.join("main.rs")).unwrap()
+}
+
+#[test]
+fn inserts_in_order() {
+  let root = src_root(
+    "xtask_utils::tasks!(\n  // builds\n  build\n  // a comment\n  // about test\n  test\n);\n",
+  );
+  NewTask::new(&*root, "lint").create().unwrap();
+  assert_eq!(
+    main_rs(&root),
+    "xtask_utils::tasks!(\n  // builds\n  build\n  lint\n  // a comment\n  // about test\n  test\n);\n",
+  );
+}
+
+#[test]
+fn skips_comments() {
+  let root = src_root(
+    "// tasks!(ignored)\ntasks!(\n  // builds (release)\n  build\n  test\n);\n",
+  );
+  NewTask::new(&*root, "lint").create().unwrap();
+  assert_eq!(
+    main_rs(&root),
+    "// tasks!(ignored)\ntasks!(\n  // builds (release)\n  build\n  lint\n  test\n);\n",
+  );
+
+  let err = NewTask::new(&*root, "test").create().unwrap_err();
+  assert!(err.to_string().contains("failed to update"));
+  assert!(!root.join("test.rs").exists());
+}
+
+#[test]
+fn appends_when_unsorted() {
+  let root = src_root("tasks! {\n    test\n    build\n}\n");
+  NewTask::new(&*root, "lint").create().unwrap();
+  assert_eq!(
+    main_rs(&root),
+    "tasks! {\n    test\n    build\n    lint\n}\n"
+  );
+}
+
+#[test]
+fn appends_after_last() {
+  let root = src_root("tasks!(\n  build\n  lint\n);\n");
+  NewTask::new(&*root, "test").create().unwrap();
+  assert_eq!(main_rs(&root), "tasks!(\n  build\n  lint\n  test\n);\n");
+}
+
+#[test]
+fn single_line() {
+  let root = src_root("xtask_utils::tasks!(build test);\n");
+  NewTask::new(&*root, "lint").create().unwrap();
+  assert_eq!(main_rs(&root), "xtask_utils::tasks!(build lint test);\n");
+}
+
+#[test]
+fn preserves_crlf() {
+  let root = src_root("tasks!(\r\n  build\r\n  test\r\n);\r\n");
+  let path = NewTask::new(&*root, "lint").create().unwrap();
+  assert_eq!(
+    main_rs(&root),
+    "tasks!(\r\n  build\r\n  lint\r\n  test\r\n);\r\n"
+  );
+  let task = fs::read_to_string(path).unwrap();
+  assert!(task.contains("\r\n"));
+  assert!(!task.replace("\r\n", "").contains('\n'));
+}
+
+#[test]
+fn renders_template() {
+  let root = src_root("tasks!(build);\n");
+  let path = NewTask::new(&*root, "lint")
+    .about("Run the linters")
+    .returns(Returns::Result)
+    .arg(Arg::positional("path").help("What to lint"))
+    .arg(Arg::flag("fix"))
+    .create()
+    .unwrap();
+  assert_eq!(path, root.join("lint.rs"));
+  assert_eq!(
+    fs::read_to_string(path).unwrap(),
+    "use clap::Parser;
+use xtask_utils::{any_err::AnyErr, prelude::*};
+
+#[derive(Parser)]
+/// Run the linters
+pub struct Cli {
+    /// What to lint
+    path: String,
+
+    #[arg(long)]
+    fix: bool,
+}
+
+pub fn main(cli: Cli) -> ! {
+    run(cli).exit();
+}
+
+fn run(Cli { path, fix }: Cli) -> Result<(), AnyErr> {
+    Ok(())
+}
+"
+  );
+}
+
+#[test]
+fn rejects_duplicates() {
+  let root = src_root("tasks!(build lint);\n");
+  let err = NewTask::new(&*root, "lint").create().unwrap_err();
+  assert!(err.to_string().contains("failed to update"));
+  assert!(!root.join("lint.rs").exists());
+
+  fs::write(root.join("test.rs"), "").unwrap();
+  let err = NewTask::new(&*root, "test").create().unwrap_err();
+  assert!(err.to_string().contains("already exists"));
+  assert_eq!(main_rs(&root), "tasks!(build lint);\n");
+}
+
+#[test]
+fn rejects_bad_names() {
+  let root = src_root("tasks!(build);\n");
+  for name in ["Lint", "lint-all", "1lint", "", "mod"] {
+    assert!(NewTask::new(&*root, name).create().is_err(), "{name}");
+  }
+  assert!(NewTask::new(&*root, "lint")
+    .arg(Arg::option("Path"))
+    .create()
+    .is_err());
+  assert_eq!(main_rs(&root), "tasks!(build);\n");
+}
@@ -0,0 +1,131 @@
+use self::common::*;
+use std::{
+  fs,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use xtask_utils::{any_err::AnyErr, step::Step, walk::Walk};
+
+mod common;
+
+/// A unique name for the step and a directory with its inputs
+fn setup(name: &str) -> (String, TempDir) {
+  let dir = TempDir::new(&format!("step-{name}"));
+  // the cache of a step outlives the directory, so the name has to be unique
+  // across test runs
+  let name = format!(
+    "test-{name}-{}",
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap()
+      .as_micros()
+  );
+  fs::create_dir_all(dir.join("src")).unwrap();
+  fs::write(dir.join("src/a.txt"), "a").unwrap();
+  fs::write(dir.join("src/b.md"), "b").unwrap();
+  (name, dir)
+}
+
+fn ok() -> Result<(), AnyErr> {
+  Ok(())
+}
+
+#[test]
+fn skips_fresh_steps() {
+  let (name, dir) = setup("fresh");
+  let step = || Step::new(&name).input(dir.join("src"));
+
+  assert!(!step().is_fresh().unwrap());
+  assert!(step().run(ok).unwrap());
+  assert!(step().is_fresh().unwrap());
+  assert!(!step().run(ok).unwrap());
+
+  fs::write(dir.join("src/b.md"), "changed").unwrap();
+  assert!(step().run(ok).unwrap());
+  assert!(!step().run(ok).unwrap());
+
+  fs::write(dir.join("src/c.txt"), "new").unwrap();
+  assert!(step().run(ok).unwrap());
+
+  assert!(step().arg("--release").run(ok).unwrap());
+  assert!(!step().arg("--release").run(ok).unwrap());
+}
+
+#[test]
+fn walk_inputs() {
+  let (name, dir) = setup("walk");
+  let step = || Step::new(&name).inputs(Walk::new(&*dir).include("*.txt"));
+
+  assert!(step().run(ok).unwrap());
+  fs::write(dir.join("src/b.md"), "not an input").unwrap();
+  assert!(!step().run(ok).unwrap());
+  fs::write(dir.join("src/a.txt"), "changed").unwrap();
+  assert!(step().run(ok).unwrap());
+}
+
+#[test]
+fn missing_output() {
+  let (name, dir) = setup("output");
+  let output = dir.join("out.txt");
+  let step = || Step::new(&name).input(dir.join("src")).output(&output);
+
+  assert!(step().run(|| fs::write(&output, "out")).unwrap());
+  assert!(!step().run(ok).unwrap());
+  fs::remove_file(&output).unwrap();
+  assert!(step().run(|| fs::write(&output, "out")).unwrap());
+}
+
+#[test]
+fn failed_run() {
+  let (name, dir) = setup("failed");
+  let step = || Step::new(&name).input(dir.join("src"));
+
+  assert!(step().run(ok).unwrap());
+  fs::write(dir.join("src/a.txt"), "changed").unwrap();
+  let err = step()
+    .run(|| Err(AnyErr::new("generator failed")))
+    .unwrap_err();
+  assert_eq!(err.to_string(), "generator failed");
+  assert!(!step().is_fresh().unwrap());
+
+  fs::write(dir.join("src/a.txt"), "a").unwrap();
+  assert!(step().run(ok).unwrap());
+}
+
+#[test]
+fn missing_input() {
+  let (name, dir) = setup("missing");
+  let missing = dir.join("missing.txt");
+  let err = Step::new(name).input(&missing).run(ok).unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    format!("failed to read {}", missing.display())
+  );
+}
+
+#[test]
+fn forced() {
+  let (name, dir) = setup("forced");
+  let name: &str = name.leak();
+  let input: &str = dir.join("src").display().to_string().leak();
+
+  assert_eq!(run_xtask(true, ["step", name, input]), "ran\n");
+  assert_eq!(run_xtask(true, ["step", name, input]), "skipped\n");
+  assert_eq!(run_xtask(true, ["--force", "step", name, input]), "ran\n");
+  assert_eq!(
+    run_xtask(true, ["--force", "step", name, input, "--", "pass"]),
+    "ran\npass\n"
+  );
+}
+
+#[cfg(feature = "script")]
+#[test]
+fn pipe_command_line() {
+  use xtask_utils::cmd;
+
+  let (name, dir) = setup("pipe");
+  let step = || Step::new(&name).input(dir.join("src"));
+
+  assert!(step().pipe(cmd!("echo one")).unwrap());
+  assert!(!step().pipe(cmd!("echo one")).unwrap());
+  assert!(step().pipe(cmd!("echo two")).unwrap());
+}
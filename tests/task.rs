@@ -0,0 +1,89 @@
+use self::common::*;
+use std::fs;
+
+mod common;
+
+#[test]
+fn single_task() {
+  assert_eq!(run_xtask(true, ["echo", "hello", "world"]), "hello world\n");
+}
+
+#[test]
+fn chained_by_name() {
+  assert_eq!(run_xtask(true, ["pass", "echo", "pass"]), "pass\n\npass\n");
+}
+
+#[test]
+fn chained_with_args() {
+  assert_eq!(
+    run_xtask(true, ["echo", "hello", "--", "echo", "world"]),
+    "hello\nworld\n"
+  );
+}
+
+#[test]
+fn passes_separator_to_task() {
+  assert_eq!(run_xtask(true, ["echo", "--", "--x"]), "--x\n");
+}
+
+#[test]
+fn chains_in_nested_runs() {
+  assert_eq!(run_xtask(true, ["pass", "--", "nested"]), "pass\na\nb\n");
+}
+
+#[test]
+fn stops_at_first_failure() {
+  assert_eq!(
+    run_xtask(
+      false,
+      ["echo", "before", "--", "fail", "--", "echo", "after"]
+    ),
+    "before\n"
+  );
+}
+
+#[test]
+fn rejects_unknown_task_before_running() {
+  assert_eq!(run_xtask(false, ["pass", "--", "nope"]), "");
+}
+
+#[test]
+fn timings_json() {
+  let dir = TempDir::new("task-timings");
+  let path = dir.join("timings.json");
+  let arg = format!("--timings-json={}", path.display()).leak();
+
+  run_xtask(false, [arg, "script", "--", "fail", "--", "pass"]);
+
+  let json = fs::read_to_string(&path).unwrap_or_else(|_| {
+    panic!("failed to read timings file: {}", path.display())
+  });
+  assert!(json.contains(r#""name":"script""#));
+  assert!(json.contains(r#""name":"echo one","#));
+  assert!(json.contains(r#""name":"echo two | cat","#));
+  assert!(json.contains(r#""name":"fail","duration_secs":"#));
+  assert!(
+    json.contains(r#""name":"pass","duration_secs":null,"status":"skipped""#)
+  );
+}
+
+#[test]
+fn list() {
+  let list = run_xtask(true, ["list"]);
+  assert!(list.contains("echo    Print the given words\n    [WORDS]...\n"));
+  assert!(list.contains("fail    Exit with a non-zero code\n"));
+}
+
+#[test]
+fn list_json() {
+  let list = run_xtask(true, ["list", "--json"]);
+  assert!(list.starts_with(r#"{"tasks":[{"name":"echo","about":"Print the given words","args":[{"name":"words","positional":true,"#));
+  assert!(
+    list.contains(r#"{"name":"pass","about":"Exit successfully","args":[]}"#)
+  );
+}
+
+#[test]
+fn task_name_as_argument() {
+  assert_eq!(run_xtask(true, ["echo", "pass", "fail"]), "pass fail\n");
+}
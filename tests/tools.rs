@@ -0,0 +1,143 @@
+use self::common::*;
+use std::path::{Path, PathBuf};
+use xtask_utils::{
+  tools::{self, require_tool, which},
+  workspace,
+};
+
+mod common;
+
+/// An executable script in `dir` at an absolute path, so it is found without
+/// changing the `PATH`
+#[cfg(unix)]
+fn fake_tool(dir: &Path, name: &str, script: &str) -> String {
+  use std::{fs, os::unix::fs::PermissionsExt};
+
+  let path = dir.join(name);
+  fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+  fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+  path.display().to_string()
+}
+
+#[test]
+fn finds_on_path() {
+  let cargo = env!("CARGO");
+  assert_eq!(which(cargo), Some(PathBuf::from(cargo)));
+  assert_eq!(which("xtask-utils-no-such-tool"), None);
+  #[cfg(unix)]
+  assert!(which("sh").unwrap().is_absolute());
+}
+
+#[test]
+fn missing_tool() {
+  let err = require_tool("xtask-utils-no-such-tool")
+    .hint("cargo install xtask-utils-no-such-tool")
+    .find()
+    .unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    "`xtask-utils-no-such-tool` was not found on the PATH"
+  );
+  assert_eq!(
+    err.hints().collect::<Vec<_>>(),
+    ["cargo install xtask-utils-no-such-tool"]
+  );
+}
+
+#[cfg(unix)]
+#[test]
+fn versions() {
+  let dir = TempDir::new("tools");
+  let tool = fake_tool(&dir, "tool", "echo 'tool v1.4.2-beta (2024-01-01)'");
+  assert_eq!(
+    require_tool(&tool).version(">=1.2").find().unwrap(),
+    PathBuf::from(&tool)
+  );
+  require_tool(&tool).version("1.4").find().unwrap();
+
+  let err = require_tool(&tool).version(">=2").find().unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    format!("`{tool}` 1.4.2 is installed but >=2 is required")
+  );
+
+  let err = require_tool(&tool).version("nope").find().unwrap_err();
+  assert_eq!(err.to_string(), "invalid version requirement `nope`");
+
+  let stderr = fake_tool(&dir, "stderr", "echo 'libtool 25.1' >&2");
+  require_tool(&stderr).version("~25.1").find().unwrap();
+
+  let subcommand = fake_tool(
+    &dir,
+    "cargo-sub",
+    r#"[ "$1" = sub ] && echo "cargo-sub 0.9.72""#,
+  );
+  require_tool(&subcommand)
+    .version("=0.9.72")
+    .version_args(["sub", "--version"])
+    .find()
+    .unwrap();
+
+  let none = fake_tool(&dir, "none", "echo unknown");
+  let err = require_tool(&none).version("1").find().unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    format!("no version in the output of `{none}`")
+  );
+}
+
+#[test]
+fn reports_every_tool() {
+  let err = tools::check([
+    require_tool(env!("CARGO")),
+    require_tool("xtask-utils-missing-a").hint("install a"),
+    require_tool(env!("CARGO")).version(">=1"),
+    require_tool("xtask-utils-missing-b").hint("install b"),
+  ])
+  .unwrap_err();
+  assert_eq!(err.to_string(), "required tools are missing");
+  let errors = err
+    .iter()
+    .map(|err| (err.to_string(), err.hints().collect::<Vec<_>>().join("")))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    errors,
+    [
+      (
+        "`xtask-utils-missing-a` was not found on the PATH".to_owned(),
+        "install a".to_owned()
+      ),
+      (
+        "`xtask-utils-missing-b` was not found on the PATH".to_owned(),
+        "install b".to_owned()
+      ),
+    ]
+  );
+
+  tools::check([require_tool(env!("CARGO"))]).unwrap();
+}
+
+// run in a subprocess since it changes the `PATH` and `CARGO`
+#[cfg(unix)]
+#[test]
+fn installs_cargo_tools() {
+  let root = workspace::metadata()
+    .unwrap()
+    .target_directory
+    .join("xtask-tools");
+  let bin = root.join("bin/xtask-utils-fake-tool");
+  let root = root.display();
+  let bin = bin.display();
+  assert_eq!(
+    run_helper(true, ["install-tools"]),
+    format!(
+      "{bin}
+{bin}
+failed to install xtask-utils-broken 1.0.0: check your registry
+install --root {root} --version 1.0.0 xtask-utils-fake-tool
+install --root {root} --version 1.1.0 xtask-utils-fake-tool
+install --root {root} --version 1.0.0 xtask-utils-broken
+"
+    )
+  );
+}
@@ -0,0 +1,91 @@
+use self::common::*;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+use xtask_utils::walk::Walk;
+
+mod common;
+
+fn tree() -> TempDir {
+  let root = TempDir::new("walk");
+  for file in [
+    "Cargo.toml",
+    "build.rs",
+    "crates/b/src/lib.rs",
+    "crates/a/src/main.rs",
+    "crates/a/README.md",
+    "crates/a/target/debug/out.rs",
+    "generated/schema.rs",
+    ".hidden/config.rs",
+  ] {
+    let path = root.join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, "").unwrap();
+  }
+  fs::write(root.join(".gitignore"), "/generated\n").unwrap();
+  root
+}
+
+fn relative(root: &Path, paths: Vec<PathBuf>) -> Vec<String> {
+  paths
+    .into_iter()
+    .map(|path| path.strip_prefix(root).unwrap().display().to_string())
+    .collect()
+}
+
+#[test]
+fn include_exclude() {
+  let root = tree();
+  let paths = Walk::new(&*root)
+    .include("*.rs")
+    .exclude("target/")
+    .paths()
+    .unwrap();
+  assert_eq!(
+    relative(&root, paths),
+    ["build.rs", "crates/a/src/main.rs", "crates/b/src/lib.rs"]
+  );
+
+  let paths = Walk::new(&*root).include("/*.rs").paths().unwrap();
+  assert_eq!(relative(&root, paths), ["build.rs"]);
+}
+
+#[test]
+fn gitignore_and_hidden() {
+  let root = tree();
+  let paths = Walk::new(&*root)
+    .include("*.rs")
+    .gitignore(false)
+    .hidden(true)
+    .paths()
+    .unwrap();
+  assert_eq!(
+    relative(&root, paths),
+    [
+      ".hidden/config.rs",
+      "build.rs",
+      "crates/a/src/main.rs",
+      "crates/a/target/debug/out.rs",
+      "crates/b/src/lib.rs",
+      "generated/schema.rs",
+    ]
+  );
+}
+
+#[test]
+fn invalid_pattern() {
+  let err = Walk::new(".").include("a[").paths().unwrap_err();
+  assert_eq!(err.to_string(), "invalid include pattern `a[`");
+}
+
+#[test]
+fn command() {
+  let root = tree();
+  let command = Walk::new(&*root).include("*.md").command("cat").unwrap();
+  assert_eq!(command.get_program(), "cat");
+  assert_eq!(
+    command.get_args().collect::<Vec<_>>(),
+    [root.join("crates/a/README.md")]
+  );
+}
@@ -0,0 +1,119 @@
+use self::common::*;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::mpsc,
+  thread,
+  time::Duration,
+};
+use xtask_utils::watch::Watch;
+
+mod common;
+
+/// A directory with an ignored subdirectory to watch
+fn watched_dir(name: &str) -> TempDir {
+  let dir = TempDir::new(&format!("watch-{name}"));
+  fs::create_dir_all(dir.join("ignored")).unwrap();
+  fs::write(dir.join(".gitignore"), "/ignored\n").unwrap();
+  dir
+}
+
+/// Write some files once the watcher is running and return the first change
+fn first_change(watch: Watch, dir: &Path) -> Vec<PathBuf> {
+  let mut changes = watch.changes().unwrap();
+  let (sender, receiver) = mpsc::channel();
+  thread::spawn(move || sender.send(changes.next()));
+
+  thread::sleep(Duration::from_millis(300));
+  fs::write(dir.join("ignored/file.txt"), "ignored").unwrap();
+  fs::write(dir.join("excluded.log"), "excluded").unwrap();
+  fs::write(dir.join("a.txt"), "a").unwrap();
+  fs::write(dir.join("b.txt"), "b").unwrap();
+  receiver
+    .recv_timeout(Duration::from_secs(10))
+    .expect("no change was seen")
+    .unwrap()
+}
+
+#[test]
+fn changes() {
+  let dir = watched_dir("native");
+  let watch = Watch::new().path(&*dir).exclude("*.log");
+  assert_eq!(
+    first_change(watch, &dir),
+    [dir.join("a.txt"), dir.join("b.txt")]
+  );
+}
+
+#[test]
+fn polling() {
+  let dir = watched_dir("poll");
+  let watch = Watch::new()
+    .path(&*dir)
+    .exclude("*.log")
+    .poll(Duration::from_millis(50))
+    .debounce(Duration::from_millis(500));
+  assert_eq!(
+    first_change(watch, &dir),
+    [dir.join("a.txt"), dir.join("b.txt")]
+  );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reruns_and_cancels() {
+  use std::{process::Command, time::Instant};
+
+  let dir = watched_dir("run");
+  // outside of `dir` so that reading it isn't seen as a change
+  let pids_dir = TempDir::new("watch-pids");
+  let pids = pids_dir.join("pids");
+  let watch = Watch::new().path(&*dir);
+  // records the pid of a grandchild and of the child
+  let mut command = Command::new("sh");
+  command.args([
+    "-c",
+    &format!(
+      "sleep 30 & echo $! >> {0}; echo $$ >> {0}; exec sleep 30",
+      pids.display()
+    ),
+  ]);
+  thread::spawn(move || watch.run_command(&mut command));
+
+  let wait_for_pids = |count| {
+    let start = Instant::now();
+    loop {
+      let pids = fs::read_to_string(&pids).unwrap_or_default();
+      if pids.lines().count() >= count {
+        return pids;
+      }
+      assert!(start.elapsed() < Duration::from_secs(10), "no rerun");
+      thread::sleep(Duration::from_millis(50));
+    }
+  };
+  wait_for_pids(2);
+  thread::sleep(Duration::from_millis(300));
+  fs::write(dir.join("a.txt"), "a").unwrap();
+  let pids = wait_for_pids(4);
+  thread::sleep(Duration::from_millis(300));
+
+  let pids = pids.lines().collect::<Vec<_>>();
+  assert_eq!(pids.len(), 4);
+  // killed processes nobody has waited for yet are zombies
+  let running = |pid| {
+    fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| {
+      !stat
+        .rsplit_once(") ")
+        .is_some_and(|(_, rest)| rest.starts_with('Z'))
+    })
+  };
+  assert!(!running(pids[0]), "the first run's grandchild is running");
+  assert!(!running(pids[1]), "the first run was not cancelled");
+  assert!(running(pids[2]));
+  assert!(running(pids[3]));
+}
+
+#[test]
+fn builtin_requires_a_task() {
+  assert_eq!(run_xtask(false, ["watch"]), "");
+}
@@ -0,0 +1,38 @@
+use std::path::Path;
+use xtask_utils::workspace;
+
+#[test]
+fn root() {
+  let root = workspace::root().unwrap();
+  assert_eq!(root, Path::new(env!("CARGO_MANIFEST_DIR")));
+  assert!(std::ptr::eq(root, workspace::root().unwrap()));
+}
+
+#[test]
+fn metadata() {
+  let metadata = workspace::metadata().unwrap();
+  assert_eq!(
+    metadata.workspace_root,
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+  );
+
+  let members: Vec<_> = metadata.members().map(|pkg| &pkg.name).collect();
+  assert_eq!(members, ["xtask-utils"]);
+
+  let package = metadata.package("xtask-utils").unwrap();
+  assert_eq!(package.version, env!("CARGO_PKG_VERSION"));
+  assert_eq!(package.dir(), Path::new(env!("CARGO_MANIFEST_DIR")));
+  assert!(package.features.contains_key("workspace"));
+  assert!(package.targets.iter().any(|target| target.is_lib()));
+  let test = package
+    .targets
+    .iter()
+    .find(|target| target.name == "workspace")
+    .unwrap();
+  assert_eq!(test.kind, ["test"]);
+  assert_eq!(test.required_features, ["workspace"]);
+  assert!(package
+    .dependencies
+    .iter()
+    .any(|dep| dep.name == "serde_json" && dep.optional));
+}